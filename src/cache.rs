@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+const CACHE_FILE_NAME: &str = "sizes.json";
+
+/// A folder's size as of the last time it was walked.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    /// The folder's own mtime when it was sized; any other value invalidates the entry
+    mtime: SystemTime,
+    on_disk: bool,
+    size: u64,
+}
+
+/// Folder sizes from earlier runs of `clean --cache`, kept in the user cache dir.
+///
+/// Entries are keyed by path and checked against the folder's own mtime, which only
+/// changes when entries directly inside it are added, removed or renamed.
+pub struct SizeCache {
+    path: Option<PathBuf>,
+    entries: Mutex<HashMap<PathBuf, CacheEntry>>,
+}
+
+/// The cache file, e.g. `~/.cache/rs-clean/sizes.json`.
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("rs-clean").join(CACHE_FILE_NAME))
+}
+
+fn folder_mtime(folder: &Path) -> Option<SystemTime> {
+    fs::metadata(folder).and_then(|m| m.modified()).ok()
+}
+
+impl SizeCache {
+    /// Read the cache file; a missing or unreadable one just means an empty cache.
+    pub fn load() -> SizeCache {
+        let path = cache_path();
+        let entries = path
+            .as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        SizeCache {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Size of `folder`, if it hasn't changed since it was cached.
+    ///
+    /// Only the folder's own mtime is checked, so a file rewritten deeper inside goes
+    /// unnoticed; that's fine for sizes, but no modification time is cached.
+    pub fn get(&self, folder: &Path, on_disk: bool) -> Option<u64> {
+        let mtime = folder_mtime(folder)?;
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(folder)?;
+        (entry.mtime == mtime && entry.on_disk == on_disk).then_some(entry.size)
+    }
+
+    pub fn insert(&self, folder: &Path, on_disk: bool, size: u64) {
+        let Some(mtime) = folder_mtime(folder) else {
+            return;
+        };
+        let entry = CacheEntry {
+            mtime,
+            on_disk,
+            size,
+        };
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(folder.to_path_buf(), entry);
+    }
+
+    /// Write the cache back, dropping folders that no longer exist.
+    pub fn save(&self) -> Result<(), String> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| "Could not determine the user cache directory.".to_string())?;
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|folder, _| folder.is_dir());
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Could not create cache dir {:?}: {}", dir, e))?;
+        }
+        let json = serde_json::to_string(&*entries)
+            .map_err(|e| format!("Could not serialize the size cache: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Could not write size cache {:?}: {}", path, e))
+    }
+}
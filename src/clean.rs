@@ -1,63 +1,1011 @@
+use crate::cache::SizeCache;
+use crate::manifest;
+use crate::term::{self, esay, say};
 use bytesize::ByteSize;
+use clap::ValueEnum;
+use crossterm::style::Color;
+use globset::{Glob, GlobMatcher, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use ignore::{WalkBuilder, WalkState};
+use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
 use std::fs;
-use std::path::{Path};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-struct CleanRule {
-    folder_name: &'static str,
-    project_indicator: Option<&'static str>,
-    description: &'static str,
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+const CONFIG_FILE_NAME: &str = "rs-clean.toml";
+
+/// Folders written to more recently than this look like a build is still running
+const BUSY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Where `--trash` sends folders; the `trash` crate picks the mechanism per platform
+#[cfg(windows)]
+const TRASH_NAME: &str = "Recycle Bin";
+#[cfg(not(windows))]
+const TRASH_NAME: &str = "Trash";
+
+/// Appended to delete errors caused by a file another process has open
+const LOCKED_HINT: &str = " - a file inside is open in another program (an editor, terminal, running build or virus scan); close it and try again";
+
+/// How often `--daemon` checks for a shutdown signal while waiting for the next run
+const DAEMON_POLL: Duration = Duration::from_millis(250);
+
+/// A root with more direct entries than this is too broad to delete under without
+/// `--i-know-what-im-doing`
+const BROAD_ROOT_ENTRIES: usize = 500;
+
+/// How long the filesystem must be quiet before `--watch` rescans
+const WATCH_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Options controlling which matched folders are reported or deleted.
+#[derive(Default)]
+pub struct CleanOptions {
+    /// Whether matched folders are only reported or actually removed
+    pub mode: Mode,
+    /// Only clean folders whose newest content is older than this many days
+    pub older_than: Option<u64>,
+    /// Only clean folders whose newest content is newer than this many days
+    pub newer_than: Option<u64>,
+    /// Ignore folders smaller than this size
+    pub min_size: Option<ByteSize>,
+    /// Ask for confirmation before deleting each folder (only meaningful in `Mode::Delete`)
+    pub interactive: bool,
+    /// Path to a rules config file (defaults to `rs-clean.toml` in the scan root)
+    pub config: Option<PathBuf>,
+    /// Only use rules from the config file
+    pub no_default_rules: bool,
+    /// Use exactly these rules instead of the defaults and any config file
+    pub rules: Option<Vec<CleanRule>>,
+    /// Restrict the scan to these rules (by folder name)
+    pub only: Vec<String>,
+    /// Leave out these rules (by folder name)
+    pub skip: Vec<String>,
+    /// How results are printed
+    pub format: OutputFormat,
+    /// How much per-folder detail human output includes
+    pub verbosity: Verbosity,
+    /// Print no report at all when nothing matched and nothing failed
+    pub silent_when_clean: bool,
+    /// .gitignore-style patterns for folders that must never be cleaned
+    pub exclude: Vec<String>,
+    /// Like `exclude`, but each pattern only protects folders matched by one rule
+    pub protect: Vec<RuleProtect>,
+    /// Honor `.gitignore`, `.ignore` and global git excludes while scanning
+    pub respect_gitignore: bool,
+    /// Descend into hidden (dot-prefixed) directories
+    pub hidden: bool,
+    /// Read ignore files from the parent directories of the scan root
+    pub parents: bool,
+    /// Maximum directory depth to scan (0 = the root only)
+    pub max_depth: Option<usize>,
+    /// Number of largest matches listed individually in the dry-run report
+    pub top: usize,
+    /// Show a live spinner while sizing folders (only drawn on a terminal)
+    pub progress: bool,
+    /// Include throughput and an ETA in the progress bar while deleting
+    pub eta: bool,
+    /// Move folders to the system trash instead of deleting them permanently
+    pub trash: bool,
+    /// Stop deleting (largest first) once this much space has been reclaimed
+    pub free_up: Option<ByteSize>,
+    /// Walk single-threaded in sorted order for reproducible runs
+    pub sequential: bool,
+    /// Treat every folder named like a rule as cleanable, even without its project indicator
+    pub ignore_indicator: bool,
+    /// How many directories above a match to look for its project indicator
+    pub indicator_search_depth: usize,
+    /// Only use rules in these categories
+    pub categories: Vec<Category>,
+    /// In a dry run, exit with code 2 when more than this could be reclaimed
+    pub fail_if_over: Option<ByteSize>,
+    /// Treat symlinked matches as cleanable; only the link itself is ever removed
+    pub follow_symlinks: bool,
+    /// After deleting, also remove ancestor directories left empty, up to the scan root
+    pub prune_empty_parents: bool,
+    /// Leave folders alone when their git repository has uncommitted changes
+    pub skip_dirty: bool,
+    /// Refuse to delete when more than this many folders match (`None` = no limit)
+    pub max_matches: Option<usize>,
+    /// Delete even when a root is `/`, the home directory or holds over `BROAD_ROOT_ENTRIES` entries
+    pub allow_broad_root: bool,
+    /// Give up sizing a single folder after this long and report its size as unknown
+    pub size_timeout: Option<Duration>,
+    /// Number of scanning threads (defaults to the number of CPUs)
+    pub threads: Option<usize>,
+    /// Delete folders even when they look in use by a running build or install
+    pub force_busy: bool,
+    /// Shell command run after the report, with the totals in `RS_CLEAN_*` variables
+    pub on_complete: Option<String>,
+    /// Reuse folder sizes from earlier runs for folders whose mtime hasn't changed; ignored when
+    /// deleting or filtering by age, which need each folder's newest mtime
+    pub cache: bool,
+    /// Also write the JSON report to this file, whatever `format` is
+    pub report: Option<PathBuf>,
+    /// Append one JSON line per deletion attempt to this audit log
+    pub log: Option<PathBuf>,
+    /// Measure allocated disk blocks instead of apparent file length (Unix only)
+    pub on_disk: bool,
 }
 
-const CLEAN_RULES: &[CleanRule] = &[
-    CleanRule {
-        folder_name: "node_modules",
-        project_indicator: Some("package.json"),
-        description: "Node.js dependencies",
-    },
-    CleanRule {
-        folder_name: "target",
-        project_indicator: Some("Cargo.toml"),
-        description: "Rust build artifacts",
-    },
-    CleanRule {
-        folder_name: "vendor",
-        project_indicator: Some("composer.json"),
-        description: "PHP dependencies",
-    },
-    CleanRule {
-        folder_name: "venv",
-        project_indicator: None,
-        description: "Python virtual environment",
-    },
-    CleanRule {
-        folder_name: ".venv",
-        project_indicator: None,
-        description: "Python virtual environment",
-    },
-    CleanRule {
-        folder_name: "bin",
-        project_indicator: Some("*.csproj"),
-        description: ".NET build output",
+/// A `--protect RULE:GLOB` pair: folders matched by `rule` are never cleaned under `pattern`.
+#[derive(Clone)]
+pub struct RuleProtect {
+    pub rule: String,
+    pub pattern: String,
+}
+
+/// Whether a clean run only reports matches or removes them.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    DryRun,
+    Delete,
+}
+
+/// How much per-folder detail the human output includes.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verbosity {
+    /// Only the final summary
+    Quiet,
+    #[default]
+    Normal,
+    /// Also list skipped folders and why, even when deleting
+    Verbose,
+}
+
+/// Output style for the clean report.
+#[derive(Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Emoji-decorated, human-readable lines
+    #[default]
+    Human,
+    /// A single JSON document with every match and a summary
+    Json,
+    /// One CSV row per match
+    Csv,
+    /// Aligned path, rule and size columns, printed once the scan is done
+    Table,
+    /// One JSON object per match, then a summary object
+    Jsonl,
+}
+
+/// What kind of folder a rule removes, for filtering and grouping the report.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Default, ValueEnum, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Category {
+    /// Installed packages, e.g. node_modules
+    Dependencies,
+    /// Compiler and bundler output, e.g. target
+    #[default]
+    BuildArtifacts,
+    /// Tool caches that are rebuilt on demand
+    Cache,
+    /// Python virtual environments
+    VirtualEnv,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Category::Dependencies => "Dependencies",
+            Category::BuildArtifacts => "Build artifacts",
+            Category::Cache => "Caches",
+            Category::VirtualEnv => "Virtual environments",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Why a folder that matched a rule was left alone.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case", tag = "reason")]
+pub enum SkipReason {
+    /// Matched an `--exclude` pattern, or a `--protect` pattern for its rule
+    Protected,
+    /// Smaller than `--min-size`
+    TooSmall,
+    /// Modified within the `--older-than` window
+    Recent { days: u64 },
+    /// Not modified within the `--newer-than` window
+    Stale { days: u64 },
+    /// A symlink, left alone unless `--follow-symlinks` is given
+    Symlink,
+    /// Looks in use by a running build or install, left alone unless `--force-busy` is given
+    Busy,
+    /// Inside a git repository with uncommitted changes (`--skip-dirty`)
+    Dirty,
+    /// Sizing hit `--size-timeout`, so the size, age and busy checks could not be made
+    SizeUnknown,
+}
+
+/// A folder matched by a clean rule.
+#[derive(Clone, Serialize)]
+pub struct CleanMatch {
+    pub path: PathBuf,
+    pub rule: String,
+    pub category: Category,
+    pub description: String,
+    pub size_bytes: u64,
+    /// Sizing took longer than `--size-timeout`, so `size_bytes` is unknown (reported as 0)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub size_timed_out: bool,
+    /// Set when the folder matched a rule but was filtered out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<SkipReason>,
+}
+
+/// Errors that prevent a scan from running.
+#[derive(Debug)]
+pub enum CleanError {
+    NotFound(PathBuf),
+    NotADirectory(PathBuf),
+    Config(String),
+    InvalidPattern(String),
+    /// `--newer-than` is not greater than `--older-than`, so no folder could match
+    EmptyAgeWindow {
+        older: u64,
+        newer: u64,
     },
-    CleanRule {
-        folder_name: "obj",
-        project_indicator: Some("*.csproj"),
-        description: ".NET intermediate output",
+    UnknownRules {
+        unknown: Vec<String>,
+        valid: Vec<String>,
     },
-];
+}
+
+impl fmt::Display for CleanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CleanError::NotFound(path) => write!(f, "Path {:?} does not exist.", path),
+            CleanError::NotADirectory(path) => write!(f, "{:?} is not a directory.", path),
+            CleanError::Config(msg) | CleanError::InvalidPattern(msg) => f.write_str(msg),
+            CleanError::EmptyAgeWindow { older, newer } => write!(
+                f,
+                "--newer-than {} must be greater than --older-than {}, or no folder can match.",
+                newer, older
+            ),
+            CleanError::UnknownRules { unknown, valid } => write!(
+                f,
+                "Unknown rule(s): {}. Valid rules are: {}",
+                unknown.join(", "),
+                valid.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CleanError {}
+
+/// Outcome of `delete_matches`.
+#[derive(Default)]
+pub struct DeleteReport {
+    pub deleted: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, io::Error)>,
+    pub freed_bytes: u64,
+}
+
+impl DeleteReport {
+    fn merge(&mut self, other: DeleteReport) {
+        self.deleted.extend(other.deleted);
+        self.failed.extend(other.failed);
+        self.freed_bytes += other.freed_bytes;
+    }
+}
+
+/// A match as reported by `--format json`, with its deletion outcome.
+#[derive(Serialize)]
+struct CleanResult<'a> {
+    #[serde(flatten)]
+    matched: &'a CleanMatch,
+    deleted: bool,
+}
+
+/// Totals for a whole clean run.
+#[derive(Serialize, Default)]
+struct CleanSummary {
+    matched: u64,
+    deleted: u64,
+    failed: u64,
+    total_bytes: u64,
+    dry_run: bool,
+    skipped_recent: u64,
+    skipped_stale: u64,
+    skipped_small: u64,
+    skipped_small_bytes: u64,
+    skipped_busy: u64,
+    skipped_dirty: u64,
+    skipped_unknown: u64,
+    protected: u64,
+    budget_bytes: Option<u64>,
+    untouched: usize,
+    /// Empty directories removed by `--prune-empty-parents`
+    pruned_parents: u64,
+    /// Available space on the scanned volume before and after deleting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    free_before_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    free_after_bytes: Option<u64>,
+    /// Folders and bytes per rule, largest first
+    by_rule: Vec<RuleTotal>,
+    /// Directories the walk visited, and how long the scan (not the deletion) took
+    dirs_scanned: u64,
+    scan_seconds: f64,
+}
+
+/// How much one rule contributed to a clean run.
+#[derive(Serialize)]
+struct RuleTotal {
+    rule: String,
+    folders: u64,
+    bytes: u64,
+}
+
+/// Sum up matches per rule, largest total first.
+fn rule_totals<'a>(matches: impl Iterator<Item = &'a CleanMatch>) -> Vec<RuleTotal> {
+    let mut totals: HashMap<&str, RuleTotal> = HashMap::new();
+    for m in matches {
+        let total = totals.entry(&m.rule).or_insert_with(|| RuleTotal {
+            rule: m.rule.clone(),
+            folders: 0,
+            bytes: 0,
+        });
+        total.folders += 1;
+        total.bytes += m.size_bytes;
+    }
+    let mut totals: Vec<RuleTotal> = totals.into_values().collect();
+    totals.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.rule.cmp(&b.rule)));
+    totals
+}
+
+/// One line of the `--log` audit trail.
+#[derive(Serialize)]
+struct LogEntry<'a> {
+    /// Seconds since the Unix epoch
+    time: u64,
+    path: &'a Path,
+    rule: &'a str,
+    size_bytes: u64,
+    trash: bool,
+    deleted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// A folder that could not be removed.
+#[derive(Serialize)]
+struct Failure {
+    path: PathBuf,
+    error: String,
+}
+
+/// Everything a `Formatter` needs to render the outcome of a clean run.
+#[derive(Serialize)]
+struct CleanReport<'a> {
+    matches: Vec<CleanResult<'a>>,
+    summary: CleanSummary,
+    failures: Vec<Failure>,
+}
+
+/// Renders the final report of a clean run in one of the `--format` styles.
+trait Formatter {
+    fn write_report(&self, out: &mut dyn Write, report: &CleanReport) -> io::Result<()>;
+}
+
+/// The emoji summary; per-folder lines are printed as the run progresses.
+struct Human<'a> {
+    options: &'a CleanOptions,
+}
+
+impl Formatter for Human<'_> {
+    fn write_report(&self, out: &mut dyn Write, report: &CleanReport) -> io::Result<()> {
+        // Render first so --ascii can swap the emoji in one place
+        let mut buf = Vec::new();
+        self.render(&mut buf, report)?;
+        out.write_all(term::text(&String::from_utf8_lossy(&buf)).as_bytes())
+    }
+}
+
+impl Human<'_> {
+    fn render(&self, out: &mut dyn Write, report: &CleanReport) -> io::Result<()> {
+        let summary = &report.summary;
+
+        writeln!(
+            out,
+            "⏱️  Scanned {} dirs in {:.1}s",
+            with_separators(summary.dirs_scanned),
+            summary.scan_seconds
+        )?;
+
+        if summary.skipped_recent > 0 {
+            writeln!(
+                out,
+                "⏳ Skipped {} folder(s) modified within the last {} day(s).",
+                summary.skipped_recent,
+                self.options.older_than.unwrap_or(0)
+            )?;
+        }
+        if summary.skipped_stale > 0 {
+            writeln!(
+                out,
+                "⏳ Skipped {} folder(s) not modified within the last {} day(s).",
+                summary.skipped_stale,
+                self.options.newer_than.unwrap_or(0)
+            )?;
+        }
+        if summary.skipped_small > 0 {
+            writeln!(
+                out,
+                "🔬 Skipped {} folder(s) below {} (total {}).",
+                summary.skipped_small,
+                self.options.min_size.unwrap_or_default(),
+                ByteSize(summary.skipped_small_bytes)
+            )?;
+        }
+
+        if summary.skipped_busy > 0 {
+            writeln!(
+                out,
+                "🚧 Skipped {} folder(s) that look in use by a running build (use --force-busy to delete anyway).",
+                summary.skipped_busy
+            )?;
+        }
+
+        if summary.skipped_dirty > 0 {
+            writeln!(
+                out,
+                "🌿 Skipped {} folder(s) in git repositories with uncommitted changes.",
+                summary.skipped_dirty
+            )?;
+        }
+
+        if summary.skipped_unknown > 0 {
+            writeln!(
+                out,
+                "⏱️  Skipped {} folder(s) whose sizing timed out (raise --size-timeout to check them).",
+                summary.skipped_unknown
+            )?;
+        }
+
+        if summary.protected > 0 && summary.dry_run {
+            writeln!(
+                out,
+                "🛡️  Protected {} folder(s) matching --exclude or --protect.",
+                summary.protected
+            )?;
+        }
+
+        if !report.failures.is_empty() {
+            writeln!(
+                out,
+                "\n{}",
+                term::paint(
+                    format!(
+                        "❌ {} folder(s) could not be removed:",
+                        report.failures.len()
+                    ),
+                    Color::Red
+                )
+            )?;
+            for failure in &report.failures {
+                writeln!(out, "   {:?}: {}", failure.path, failure.error)?;
+            }
+        }
+
+        if let Some(budget) = summary.budget_bytes {
+            writeln!(
+                out,
+                "\n🎯 Budget of {} {}; {} candidate folder(s) left untouched.",
+                ByteSize(budget),
+                if summary.total_bytes >= budget {
+                    "satisfied"
+                } else {
+                    "NOT satisfied"
+                },
+                summary.untouched
+            )?;
+        }
+
+        // A single rule would only repeat the total below
+        if summary.by_rule.len() > 1 {
+            writeln!(out, "\n📦 By rule:")?;
+            let width = summary
+                .by_rule
+                .iter()
+                .map(|t| t.rule.len())
+                .max()
+                .unwrap_or(0);
+            for total in &summary.by_rule {
+                writeln!(
+                    out,
+                    "   {:<width$}  {:>10} across {} folder(s)",
+                    total.rule,
+                    ByteSize(total.bytes).to_string(),
+                    total.folders,
+                )?;
+            }
+        }
+
+        if summary.matched == 0 {
+            writeln!(
+                out,
+                "{}",
+                term::paint("✨ Everything looks clean!", Color::Green)
+            )
+        } else if !summary.dry_run {
+            writeln!(
+                out,
+                "\n{}",
+                term::paint("✅ Process complete.", Color::Green)
+            )?;
+            writeln!(
+                out,
+                "{}",
+                term::paint(
+                    format!("🎉 Reclaimed space: {}", ByteSize(summary.total_bytes)),
+                    Color::Green
+                )
+            )?;
+            if let (Some(before), Some(after)) =
+                (summary.free_before_bytes, summary.free_after_bytes)
+            {
+                writeln!(
+                    out,
+                    "💾 Free space: {} -> {}",
+                    ByteSize(before),
+                    ByteSize(after)
+                )?;
+            }
+            if summary.pruned_parents > 0 {
+                writeln!(
+                    out,
+                    "🧹 Pruned {} empty parent folder(s).",
+                    summary.pruned_parents
+                )?;
+            }
+            Ok(())
+        } else {
+            writeln!(
+                out,
+                "\n{}",
+                term::paint(
+                    format!(
+                        "💡 Potential space to reclaim: {}",
+                        ByteSize(summary.total_bytes)
+                    ),
+                    Color::Cyan
+                )
+            )
+        }
+    }
+}
+
+/// A single pretty-printed JSON document.
+struct Json;
+
+impl Formatter for Json {
+    fn write_report(&self, out: &mut dyn Write, report: &CleanReport) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, report)?;
+        writeln!(out)
+    }
+}
+
+/// The last line of `--format jsonl`, after one line per match.
+#[derive(Serialize)]
+struct JsonLinesSummary<'a> {
+    summary: &'a CleanSummary,
+    failures: &'a [Failure],
+}
+
+struct JsonLines;
+
+impl Formatter for JsonLines {
+    fn write_report(&self, out: &mut dyn Write, report: &CleanReport) -> io::Result<()> {
+        // The same records as the `matches` array of `--format json`
+        for result in &report.matches {
+            serde_json::to_writer(&mut *out, result)?;
+            writeln!(out)?;
+        }
+        let last = JsonLinesSummary {
+            summary: &report.summary,
+            failures: &report.failures,
+        };
+        serde_json::to_writer(&mut *out, &last)?;
+        writeln!(out)
+    }
+}
+
+/// One CSV row per match, for spreadsheets.
+struct Csv;
+
+impl Formatter for Csv {
+    fn write_report(&self, out: &mut dyn Write, report: &CleanReport) -> io::Result<()> {
+        writeln!(out, "path,rule,description,size_bytes,deleted")?;
+        for result in &report.matches {
+            let m = result.matched;
+            writeln!(
+                out,
+                "{},{},{},{},{}",
+                csv_field(&m.path.to_string_lossy()),
+                csv_field(&m.rule),
+                csv_field(&m.description),
+                m.size_bytes,
+                result.deleted
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Aligned columns, one row per match, with a total at the bottom.
+struct Table;
+
+impl Formatter for Table {
+    fn write_report(&self, out: &mut dyn Write, report: &CleanReport) -> io::Result<()> {
+        let summary = &report.summary;
+        let mut header: Vec<String> = ["PATH", "RULE", "SIZE"].map(String::from).to_vec();
+        if !summary.dry_run {
+            header.push("STATUS".to_string());
+        }
+        let rows: Vec<Vec<String>> = report
+            .matches
+            .iter()
+            .map(|result| {
+                let m = result.matched;
+                let mut row = vec![m.path.display().to_string(), m.rule.clone(), size_label(m)];
+                if !summary.dry_run {
+                    let status = if result.deleted { "deleted" } else { "kept" };
+                    row.push(status.to_string());
+                }
+                row
+            })
+            .collect();
+        let total = vec![
+            format!("TOTAL ({} folder(s))", summary.matched),
+            String::new(),
+            ByteSize(summary.total_bytes).to_string(),
+        ];
+
+        let mut widths = vec![0; header.len()];
+        for row in std::iter::once(&header).chain(&rows).chain([&total]) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let separator: Vec<String> = widths.iter().map(|&w| "-".repeat(w)).collect();
+
+        // Sizes are right-aligned so their units line up
+        let write_row = |out: &mut dyn Write, row: &[String]| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(i, (cell, &width))| match i {
+                    2 => format!("{:>width$}", cell),
+                    _ => format!("{:<width$}", cell),
+                })
+                .collect();
+            writeln!(out, "{}", cells.join("  ").trim_end())
+        };
+
+        write_row(out, &header)?;
+        write_row(out, &separator)?;
+        for row in &rows {
+            write_row(out, row)?;
+        }
+        write_row(out, &separator)?;
+        write_row(out, &total)
+    }
+}
+
+/// `n` with a comma between each group of three digits, e.g. `48,213`.
+fn with_separators(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Answer given at an interactive delete prompt.
+enum Answer {
+    Yes,
+    No,
+    All,
+    Quit,
+}
+
+/// A kind of folder to clean, e.g. `node_modules` next to a `package.json`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CleanRule {
+    /// Directory name to match; may be a glob such as `cmake-build-*`
+    pub folder_name: String,
+    /// The rule applies if any of these exist next to the folder; empty means always
+    #[serde(default, alias = "project_indicator", deserialize_with = "one_or_many")]
+    pub project_indicators: Vec<String>,
+    /// The folder itself must contain one of these, e.g. `Debug` or `*.dll`; empty means anything
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub contents: Vec<String>,
+    /// Only look for the indicator right next to the folder, whatever `--indicator-search-depth` says
+    #[serde(default)]
+    pub indicator_in_parent: bool,
+    #[serde(default)]
+    pub category: Category,
+    pub description: String,
+}
+
+impl CleanRule {
+    pub fn new(
+        folder_name: &str,
+        project_indicators: &[&str],
+        category: Category,
+        description: &str,
+    ) -> Self {
+        CleanRule {
+            folder_name: folder_name.to_string(),
+            project_indicators: project_indicators.iter().map(|s| s.to_string()).collect(),
+            contents: Vec::new(),
+            indicator_in_parent: false,
+            category,
+            description: description.to_string(),
+        }
+    }
+
+    /// Only match folders containing one of `contents`.
+    pub fn with_contents(mut self, contents: &[&str]) -> Self {
+        self.contents = contents.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Require the project indicator in the folder's immediate parent.
+    pub fn indicator_in_parent(mut self) -> Self {
+        self.indicator_in_parent = true;
+        self
+    }
+}
+
+/// What a .NET `bin` or `obj` folder holds after a build.
+const DOTNET_OUTPUT: &[&str] = &["Debug", "Release", "*.dll"];
+
+/// Built-in rules, used unless `--no-default-rules` is given.
+pub fn default_rules() -> Vec<CleanRule> {
+    use Category::*;
+    vec![
+        CleanRule::new(
+            "node_modules",
+            &["package.json"],
+            Dependencies,
+            "Node.js dependencies",
+        ),
+        CleanRule::new(
+            "target",
+            &["Cargo.toml"],
+            BuildArtifacts,
+            "Rust build artifacts",
+        ),
+        CleanRule::new(
+            "vendor",
+            &["composer.json"],
+            Dependencies,
+            "PHP dependencies",
+        ),
+        CleanRule::new("venv", &[], VirtualEnv, "Python virtual environment"),
+        CleanRule::new(".venv", &[], VirtualEnv, "Python virtual environment"),
+        // `bin` is also a common name for a scripts folder, so only take ones holding build output
+        CleanRule::new(
+            "bin",
+            &["*.csproj", "*.fsproj", "*.vbproj"],
+            BuildArtifacts,
+            ".NET build output",
+        )
+        .with_contents(DOTNET_OUTPUT)
+        .indicator_in_parent(),
+        CleanRule::new(
+            "obj",
+            &["*.csproj", "*.fsproj", "*.vbproj"],
+            BuildArtifacts,
+            ".NET intermediate output",
+        )
+        .with_contents(DOTNET_OUTPUT)
+        .indicator_in_parent(),
+        CleanRule::new(
+            ".next",
+            &["next.config.js", "next.config.mjs", "next.config.ts"],
+            BuildArtifacts,
+            "Next.js build output",
+        ),
+        CleanRule::new(".nuxt", &[], BuildArtifacts, "Nuxt build output"),
+        CleanRule::new(
+            "elm-stuff",
+            &["elm.json"],
+            Dependencies,
+            "Elm packages and build output",
+        ),
+        CleanRule::new(
+            ".gradle",
+            &["build.gradle", "build.gradle.kts"],
+            Cache,
+            "Gradle cache",
+        ),
+        CleanRule::new(".dart_tool", &["pubspec.yaml"], Cache, "Dart tool cache"),
+        CleanRule::new("__pycache__", &[], Cache, "Python bytecode cache"),
+        CleanRule::new(".pytest_cache", &[], Cache, "pytest cache"),
+        CleanRule::new(".mypy_cache", &[], Cache, "mypy cache"),
+        CleanRule::new(".turbo", &[], Cache, "Turborepo cache"),
+        CleanRule::new(
+            "cmake-build-*",
+            &["CMakeLists.txt"],
+            BuildArtifacts,
+            "CMake build directories",
+        ),
+    ]
+}
+
+/// Shape of the `rs-clean.toml` config file.
+#[derive(Deserialize)]
+struct RulesConfig {
+    #[serde(default)]
+    rules: Vec<CleanRule>,
+}
+
+/// The rules config in effect: `--config` if given, else `rs-clean.toml` in the root if present.
+/// Explicit `options.rules` leave no room for a config.
+fn config_path(root: &Path, options: &CleanOptions) -> Option<PathBuf> {
+    if options.rules.is_some() {
+        return None;
+    }
+    match &options.config {
+        Some(path) => Some(path.clone()),
+        None => Some(root.join(CONFIG_FILE_NAME)).filter(|p| p.is_file()),
+    }
+}
+
+/// Build the active rule set from the defaults and an optional config file.
+///
+/// The config is taken from `--config` if given, otherwise from `rs-clean.toml`
+/// in the scan root if present. User rules replace defaults with the same `folder_name`.
+/// Explicit `options.rules` bypass both.
+fn load_rules(root: &Path, options: &CleanOptions) -> Result<Vec<CleanRule>, CleanError> {
+    let mut rules = match &options.rules {
+        Some(rules) => rules.clone(),
+        None if options.no_default_rules => Vec::new(),
+        None => default_rules(),
+    };
+
+    if let Some(path) = config_path(root, options) {
+        let text = fs::read_to_string(&path)
+            .map_err(|e| CleanError::Config(format!("Could not read config {:?}: {}", path, e)))?;
+        let config: RulesConfig = toml::from_str(&text)
+            .map_err(|e| CleanError::Config(format!("Invalid config {:?}: {}", path, e)))?;
+
+        for rule in config.rules {
+            match rules.iter_mut().find(|r| r.folder_name == rule.folder_name) {
+                Some(existing) => *existing = rule,
+                None => rules.push(rule),
+            }
+        }
+    }
+
+    if !options.only.is_empty() || !options.skip.is_empty() {
+        let unknown: Vec<String> = options
+            .only
+            .iter()
+            .chain(&options.skip)
+            .filter(|name| !rules.iter().any(|r| &r.folder_name == *name))
+            .cloned()
+            .collect();
+        if !unknown.is_empty() {
+            return Err(CleanError::UnknownRules {
+                unknown,
+                valid: rules.iter().map(|r| r.folder_name.clone()).collect(),
+            });
+        }
+
+        rules.retain(|r| {
+            (options.only.is_empty() || options.only.contains(&r.folder_name))
+                && !options.skip.contains(&r.folder_name)
+        });
+    }
+
+    if !options.categories.is_empty() {
+        rules.retain(|r| options.categories.contains(&r.category));
+    }
+
+    if rules.is_empty() {
+        return Err(CleanError::Config("No clean rules configured.".to_string()));
+    }
+    Ok(rules)
+}
+
+/// Compile each rule's `folder_name` into a matcher for directory names.
+fn rule_matchers(rules: &[CleanRule]) -> Result<Vec<GlobMatcher>, CleanError> {
+    rules
+        .iter()
+        .map(|rule| {
+            Glob::new(&rule.folder_name)
+                .map(|glob| glob.compile_matcher())
+                .map_err(|e| {
+                    CleanError::InvalidPattern(format!(
+                        "Invalid folder_name {:?}: {}",
+                        rule.folder_name, e
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// Accept either a single indicator string or a list of them in the config.
+fn one_or_many<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(s) => vec![s],
+        OneOrMany::Many(v) => v,
+    })
+}
+
+/// Look for an indicator in `parent` and up to `depth` directories above it.
+///
+/// The search never leaves the scan root and stops at a repository root (a
+/// directory with `.git`), so an unrelated project further up can't vouch for the folder.
+fn find_indicator(root: &Path, parent: &Path, indicators: &[String], depth: usize) -> bool {
+    let mut dir = parent;
+    for _ in 0..=depth {
+        if matches_indicators(dir, indicators) {
+            return true;
+        }
+        if dir == root || dir.join(".git").exists() {
+            return false;
+        }
+        match dir.parent() {
+            Some(next) if next.starts_with(root) => dir = next,
+            _ => return false,
+        }
+    }
+    false
+}
+
+fn matches_indicators(parent: &Path, indicators: &[String]) -> bool {
+    indicators.is_empty()
+        || indicators
+            .iter()
+            .any(|indicator| matches_indicator(parent, indicator))
+}
 
 fn matches_indicator(parent: &Path, indicator: &str) -> bool {
     if indicator.contains('*') {
         if let Ok(entries) = fs::read_dir(parent) {
             for entry in entries.flatten() {
-                if let Some(ext) = indicator.strip_prefix("*.") {
-                    if entry.path().extension().map_or(false, |e| e == ext) {
-                        return true;
-                    }
+                if let Some(ext) = indicator.strip_prefix("*.")
+                    && entry.path().extension().is_some_and(|e| e == ext)
+                {
+                    return true;
                 }
             }
         }
@@ -67,126 +1015,2027 @@ fn matches_indicator(parent: &Path, indicator: &str) -> bool {
     }
 }
 
-/// Calculate directory size using Rayon for parallelism
-fn calculate_size(path: &Path) -> u64 {
+/// Size and newest modification time of everything inside a folder.
+struct FolderStats {
+    size: u64,
+    newest_mtime: Option<SystemTime>,
+}
+
+/// Space a file takes up: its apparent length, or with `on_disk` the blocks allocated for it.
+fn file_size(metadata: &fs::Metadata, on_disk: bool) -> u64 {
+    #[cfg(unix)]
+    if on_disk {
+        use std::os::unix::fs::MetadataExt;
+        // st_blocks is always in 512-byte units, whatever the filesystem block size
+        return metadata.blocks() * 512;
+    }
+    #[cfg(not(unix))]
+    let _ = on_disk;
+    metadata.len()
+}
+
+/// Calculate directory size and newest mtime in a single pass, using Rayon for parallelism
+fn calculate_stats(path: &Path, on_disk: bool) -> FolderStats {
+    // Size every file regardless of hidden/ignore rules; only the scan honors those.
+    // Links are never followed so a link to a shared cache isn't counted as ours.
     WalkBuilder::new(path)
+        .standard_filters(false)
+        .follow_links(false)
         .build()
         .par_bridge()
         .filter_map(|e| e.ok())
-        .map(|e| {
-            if e.path().is_file() {
-                e.metadata().map(|m| m.len()).unwrap_or(0)
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| FolderStats {
+            size: if m.is_file() {
+                file_size(&m, on_disk)
             } else {
                 0
-            }
+            },
+            newest_mtime: m.modified().ok(),
         })
-        .sum()
+        .reduce(
+            || FolderStats {
+                size: 0,
+                newest_mtime: None,
+            },
+            |a, b| FolderStats {
+                size: a.size + b.size,
+                newest_mtime: a.newest_mtime.max(b.newest_mtime),
+            },
+        )
 }
 
-pub fn clean_projects(root: &Path, force: bool) {
-    if !root.exists() {
-        eprintln!("❌ Error: Path {:?} does not exist.", root);
-        eprintln!(
-            "Hint: If you are on Windows, ensure you use forward slashes (/) or quote the path if it contains backslashes (\\)."
-        );
-        return;
+/// `git status` of each repository met during a scan, for `--skip-dirty`.
+#[derive(Default)]
+struct DirtyRepos {
+    /// Changed paths relative to the repository root; `None` when git could not tell
+    changes: Mutex<HashMap<PathBuf, Option<Vec<PathBuf>>>>,
+}
+
+impl DirtyRepos {
+    /// Whether the repository around `folder` has changes outside `folder` itself.
+    ///
+    /// An untracked `target/` would otherwise make every repo without a .gitignore
+    /// look dirty. Repositories git can't read count as dirty, to stay on the safe side.
+    fn contains(&self, folder: &Path) -> bool {
+        let Some(repo) = folder
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.join(".git").exists())
+        else {
+            return false;
+        };
+        let cached = self.changes.lock().unwrap().get(repo).cloned();
+        let changes = cached.unwrap_or_else(|| {
+            let changes = git_changes(repo);
+            self.changes
+                .lock()
+                .unwrap()
+                .insert(repo.to_path_buf(), changes.clone());
+            changes
+        });
+        let inside = folder.strip_prefix(repo).unwrap_or(folder);
+        changes.is_none_or(|changes| changes.iter().any(|path| !path.starts_with(inside)))
     }
+}
 
-    if !root.is_dir() {
-        eprintln!("❌ Error: {:?} is not a directory.", root);
-        return;
+/// Paths `git status` reports as modified, staged or untracked in `repo`.
+fn git_changes(repo: &Path) -> Option<Vec<PathBuf>> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["status", "--porcelain", "-z"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut entries = text.split('\0').filter(|entry| entry.len() > 3);
+    let mut changes = Vec::new();
+    while let Some(entry) = entries.next() {
+        changes.push(PathBuf::from(&entry[3..]));
+        // Renames and copies are followed by their original path
+        if entry.starts_with(['R', 'C']) {
+            entries.next();
+        }
+    }
+    Some(changes)
+}
+
+/// Like [`calculate_stats`], but gives up after `timeout` so a hung mount can't stall the run.
+///
+/// The sizing thread can't be cancelled; on timeout it is left to finish (or hang) on its own.
+fn stats_with_timeout(
+    path: &Path,
+    on_disk: bool,
+    timeout: Option<Duration>,
+) -> Option<FolderStats> {
+    let Some(timeout) = timeout else {
+        return Some(calculate_stats(path, on_disk));
+    };
+    // No time at all: don't race a sizing thread that could still win
+    if timeout.is_zero() {
+        return None;
     }
+    let (tx, rx) = mpsc::channel();
+    let path = path.to_path_buf();
+    thread::spawn(move || {
+        let _ = tx.send(calculate_stats(&path, on_disk));
+    });
+    rx.recv_timeout(timeout).ok()
+}
 
-    println!("🔍 Scanning path: {:?}", root);
-    if !force {
-        println!("⚠️  DRY RUN: No folders will be deleted. Use --force to delete.\n");
+/// A match's size for display, which may be unknown after `--size-timeout`.
+fn size_label(m: &CleanMatch) -> String {
+    if m.size_timed_out {
+        "unknown (timeout)".to_string()
     } else {
-        println!("⚠️  DELETING MODE: Folders will be permanently removed.\n");
-    }
-
-    let total_freed = Arc::new(AtomicU64::new(0));
-    let found_any = Arc::new(AtomicU64::new(0));
-
-    // Parallel walker to check matches
-    WalkBuilder::new(root)
-        .threads(num_cpus::get())
-        .build_parallel()
-        .run(|| {
-            let total_freed = total_freed.clone();
-            let found_any = found_any.clone();
-            Box::new(move |entry| {
-                let entry = match entry {
-                    Ok(e) => e,
-                    Err(_) => return WalkState::Continue,
-                };
-
-                let path = entry.path();
-                if path.is_dir() {
-                    let folder_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-
-                    for rule in CLEAN_RULES {
-                        if folder_name == rule.folder_name {
-                            let parent = path.parent().unwrap_or_else(|| Path::new("."));
-                            // We need to check if indicator exists.
-                            // Since we are inside a parallel walker, simple exists() check is fine,
-                            // but we should avoid expensive ops if possible.
-                            // matches_indicator is reasonably fast (stat check).
-                            let should_clean = match rule.project_indicator {
-                                Some(ind) => matches_indicator(parent, ind),
-                                None => true,
-                            };
-
-                            if should_clean {
-                                found_any.fetch_add(1, Ordering::Relaxed);
-
-                                // Calculate size before deleting (or just for reporting)
-                                let size = calculate_size(path);
-                                let size_str = ByteSize(size).to_string();
-
-                                if force {
-                                    // print! macro might interleave lines in parallel.
-                                    // For a CLI tool, usually line buffering handles it okay, but let's see.
-                                    println!(
-                                        "🗑️  Deleting {:?} ({}) - freeing {}...",
-                                        path, rule.description, size_str
-                                    );
-                                    match fs::remove_dir_all(path) {
-                                        Ok(_) => {
-                                            total_freed.fetch_add(size, Ordering::Relaxed);
-                                        }
-                                        Err(e) => println!("   FAILED to delete {:?}: {}", path, e),
-                                    }
-                                } else {
-                                    println!(
-                                        "[MATCH] Found {:<12} at {:?} ({}) - size: {}",
-                                        rule.folder_name, path, rule.description, size_str
-                                    );
-                                    total_freed.fetch_add(size, Ordering::Relaxed);
-                                }
-
-                                return WalkState::Skip; // Don't scan inside the folder we just cleaned/found
-                            }
-                        }
-                    }
-                }
-                WalkState::Continue
-            })
+        ByteSize(m.size_bytes).to_string()
+    }
+}
+
+/// Heuristic for a folder that a build or package install is writing to right now.
+fn looks_busy(path: &Path, newest_mtime: Option<SystemTime>) -> bool {
+    let fresh = |time: Option<SystemTime>| {
+        time.and_then(|t| t.elapsed().ok())
+            .is_some_and(|age| age < BUSY_WINDOW)
+    };
+    let modified = |file: PathBuf| fs::metadata(file).and_then(|m| m.modified()).ok();
+
+    if fresh(newest_mtime) {
+        return true;
+    }
+
+    // Cargo holds an exclusive lock on target/<profile>/.cargo-lock for the whole build
+    let cargo_locked = fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .any(|profile| {
+            fs::File::open(profile.path().join(".cargo-lock"))
+                .is_ok_and(|lock| lock.try_lock().is_err())
         });
+    if cargo_locked {
+        return true;
+    }
+
+    // Lock files next to the folder are rewritten while dependencies are resolved
+    let parent = path.parent().unwrap_or(path);
+    ["Cargo.lock", "package-lock.json"]
+        .iter()
+        .any(|lock| fresh(modified(parent.join(lock))))
+        || fresh(modified(path.join(".package-lock.json")))
+}
 
-    let count = found_any.load(Ordering::Relaxed);
-    let bytes = total_freed.load(Ordering::Relaxed);
+/// Run the `--on-complete` command through the system shell with the run's totals.
+fn run_hook(command: &str, summary: &CleanSummary) {
+    #[cfg(windows)]
+    let mut shell = {
+        let mut shell = std::process::Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+    #[cfg(not(windows))]
+    let mut shell = {
+        let mut shell = std::process::Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
 
-    if count == 0 {
-        println!("✨ Everything looks clean!");
-    } else {
-        if force {
-            println!("\n✅ Process complete.");
-            println!("🎉 Reclaimed space: {}", ByteSize(bytes).to_string());
+    let status = shell
+        .arg(command)
+        .env("RS_CLEAN_FREED_BYTES", summary.total_bytes.to_string())
+        .env("RS_CLEAN_COUNT", summary.matched.to_string())
+        .env("RS_CLEAN_DELETED", summary.deleted.to_string())
+        .env("RS_CLEAN_FAILED", summary.failed.to_string())
+        .env("RS_CLEAN_DRY_RUN", if summary.dry_run { "1" } else { "0" })
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => term::warning(format!("--on-complete command exited with {}", status)),
+        Err(e) => term::warning(format!("Could not run --on-complete command: {}", e)),
+    }
+}
+
+/// Ask the user whether to delete `path`. Anything unrecognised counts as "no".
+fn prompt_delete(path: &Path, size: u64) -> Answer {
+    print!("Delete {:?} ({})? [y/N/a/q] ", path, ByteSize(size));
+    let _ = io::stdout().flush();
+
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line).unwrap_or(0) == 0 {
+        // EOF on stdin: treat as abort so we never delete without an answer
+        return Answer::Quit;
+    }
+    match line.trim().to_lowercase().as_str() {
+        "y" | "yes" => Answer::Yes,
+        "a" | "all" => Answer::All,
+        "q" | "quit" => Answer::Quit,
+        _ => Answer::No,
+    }
+}
+
+/// Compile `--exclude` patterns with .gitignore syntax, relative to the scan root.
+///
+/// As in a .gitignore, later patterns override earlier ones, so `!keep/node_modules`
+/// after `**/node_modules` brings that one folder back.
+fn build_excludes(root: &Path, patterns: &[String]) -> Result<Gitignore, CleanError> {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in patterns {
+        let (negate, glob) = match pattern.strip_prefix('!') {
+            Some(rest) => ("!", rest),
+            None => ("", pattern.as_str()),
+        };
+        // Absolute patterns are anchored at the root; ones outside it can never match
+        let glob = if Path::new(glob).is_absolute() {
+            let relative = Path::new(glob).strip_prefix(root).or_else(|_| {
+                let absolute_root = std::path::absolute(root).unwrap_or_default();
+                Path::new(glob).strip_prefix(absolute_root)
+            });
+            match relative {
+                Ok(relative) => format!("/{}", relative.to_string_lossy()),
+                Err(_) => continue,
+            }
         } else {
-            println!(
-                "\n💡 Potential space to reclaim: {}",
-                ByteSize(bytes).to_string()
+            glob.strip_prefix("./").unwrap_or(glob).to_string()
+        };
+        builder
+            .add_line(None, &format!("{}{}", negate, glob))
+            .map_err(|e| {
+                CleanError::InvalidPattern(format!("Invalid exclude pattern {:?}: {}", pattern, e))
+            })?;
+    }
+    builder
+        .build()
+        .map_err(|e| CleanError::InvalidPattern(format!("Invalid exclude patterns: {}", e)))
+}
+
+/// Compile the `--protect` patterns into one matcher per rule, rejecting unknown rules.
+fn build_protects(
+    root: &Path,
+    rules: &[CleanRule],
+    protects: &[RuleProtect],
+) -> Result<HashMap<String, Gitignore>, CleanError> {
+    let unknown: Vec<String> = protects
+        .iter()
+        .filter(|p| !rules.iter().any(|r| r.folder_name == p.rule))
+        .map(|p| p.rule.clone())
+        .collect();
+    if !unknown.is_empty() {
+        return Err(CleanError::UnknownRules {
+            unknown,
+            valid: rules.iter().map(|r| r.folder_name.clone()).collect(),
+        });
+    }
+
+    let mut by_rule: HashMap<String, Vec<String>> = HashMap::new();
+    for protect in protects {
+        by_rule
+            .entry(protect.rule.clone())
+            .or_default()
+            .push(protect.pattern.clone());
+    }
+    by_rule
+        .into_iter()
+        .map(|(rule, patterns)| Ok((rule, build_excludes(root, &patterns)?)))
+        .collect()
+}
+
+/// Number of whole days elapsed since `time`, or 0 if it lies in the future.
+fn days_since(time: SystemTime) -> u64 {
+    SystemTime::now()
+        .duration_since(time)
+        .map(|d| d.as_secs() / SECS_PER_DAY)
+        .unwrap_or(0)
+}
+
+/// The largest `top` matches of each category, under a heading with the category subtotal.
+fn print_top_matches(matches: &[&CleanMatch], top: usize) {
+    let mut by_size = matches.to_vec();
+    by_size.sort_by_key(|m| (m.category, std::cmp::Reverse(m.size_bytes)));
+
+    for (i, group) in by_size
+        .chunk_by(|a, b| a.category == b.category)
+        .enumerate()
+    {
+        if i > 0 {
+            say!();
+        }
+        let group_bytes: u64 = group.iter().map(|m| m.size_bytes).sum();
+        say!(
+            "📂 {} - {} folder(s), {}",
+            group[0].category,
+            group.len(),
+            ByteSize(group_bytes)
+        );
+
+        for m in group.iter().take(top) {
+            say!(
+                "[MATCH] Found {:<12} at {:?} ({}) - size: {}",
+                m.rule,
+                m.path,
+                m.description,
+                size_label(m)
+            );
+        }
+
+        let rest = &group[top.min(group.len())..];
+        if !rest.is_empty() {
+            let rest_bytes: u64 = rest.iter().map(|m| m.size_bytes).sum();
+            say!(
+                "        ... and {} more folder(s) totalling {}",
+                rest.len(),
+                ByteSize(rest_bytes)
             );
         }
     }
 }
+
+/// Number of leading matches (sorted largest first) needed to reach `budget` bytes.
+fn budget_cutoff(sorted: &[&CleanMatch], budget: u64) -> usize {
+    let mut total = 0;
+    for (i, m) in sorted.iter().enumerate() {
+        if total >= budget {
+            return i;
+        }
+        total += m.size_bytes;
+    }
+    sorted.len()
+}
+
+/// Print one line per folder that matched a rule but was filtered out.
+fn print_skipped(matches: &[CleanMatch], options: &CleanOptions) {
+    for m in matches {
+        match m.skipped {
+            Some(SkipReason::Protected) => say!(
+                "[PROTECTED]   {:<12} at {:?} (matches --exclude or --protect)",
+                m.rule,
+                m.path
+            ),
+            Some(SkipReason::TooSmall) => say!(
+                "[SMALL]  Skip  {:<12} at {:?} ({}) - below {}",
+                m.rule,
+                m.path,
+                ByteSize(m.size_bytes),
+                options.min_size.unwrap_or_default()
+            ),
+            Some(SkipReason::Recent { days }) => say!(
+                "[RECENT] Skip  {:<12} at {:?} ({}) - modified {} day(s) ago",
+                m.rule,
+                m.path,
+                ByteSize(m.size_bytes),
+                days
+            ),
+            Some(SkipReason::Stale { days }) => say!(
+                "[STALE]  Skip  {:<12} at {:?} ({}) - last modified {} day(s) ago",
+                m.rule,
+                m.path,
+                ByteSize(m.size_bytes),
+                days
+            ),
+            Some(SkipReason::Symlink) => say!(
+                "[SYMLINK] Skip {:<12} at {:?} (use --follow-symlinks to remove the link)",
+                m.rule,
+                m.path
+            ),
+            Some(SkipReason::Busy) => say!(
+                "[BUSY]   Skip  {:<12} at {:?} - looks in use (use --force-busy to delete anyway)",
+                m.rule,
+                m.path
+            ),
+            Some(SkipReason::Dirty) => say!(
+                "[DIRTY]  Skip  {:<12} at {:?} - its git repository has uncommitted changes",
+                m.rule,
+                m.path
+            ),
+            Some(SkipReason::SizeUnknown) => say!(
+                "[TIMEOUT] Skip {:<12} at {:?} - sizing timed out, so it can't be checked",
+                m.rule,
+                m.path
+            ),
+            None => {}
+        }
+    }
+}
+
+/// Whether `--cache` can be honored. The cache is only checked against a folder's own
+/// mtime, so it can't vouch for the newest mtime inside it, which the age filters and
+/// the busy check when deleting rely on.
+fn cache_usable(options: &CleanOptions) -> bool {
+    options.cache
+        && options.older_than.is_none()
+        && options.newer_than.is_none()
+        && options.mode != Mode::Delete
+}
+
+/// Drop roots that repeat or sit inside another root, so nothing is scanned twice.
+fn dedup_roots(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut resolved: Vec<(PathBuf, &PathBuf)> = roots
+        .iter()
+        .map(|root| (root.canonicalize().unwrap_or_else(|_| root.clone()), root))
+        .collect();
+    // Sorted, an ancestor always comes right before the roots it contains
+    resolved.sort();
+    let mut kept: Vec<(PathBuf, &PathBuf)> = Vec::new();
+    for (canonical, root) in resolved {
+        if !kept.last().is_some_and(|(k, _)| canonical.starts_with(k)) {
+            kept.push((canonical, root));
+        }
+    }
+    kept.into_iter().map(|(_, root)| root.clone()).collect()
+}
+
+/// Scan several roots as one, like `scan_matches_with` on each with the results merged.
+pub fn scan_roots(
+    roots: &[PathBuf],
+    options: &CleanOptions,
+) -> Result<Vec<CleanMatch>, CleanError> {
+    scan_roots_with(roots, options, |_| {}, &AtomicU64::new(0))
+}
+
+/// Like `scan_roots`, but also hands each match to `on_match` as soon as it is found
+/// and counts every directory visited in `visited`.
+fn scan_roots_with(
+    roots: &[PathBuf],
+    options: &CleanOptions,
+    on_match: impl Fn(&CleanMatch) + Sync,
+    visited: &AtomicU64,
+) -> Result<Vec<CleanMatch>, CleanError> {
+    let mut matches = Vec::new();
+    for root in roots {
+        matches.extend(scan_root(root, options, &on_match, visited)?);
+    }
+    matches.sort_by(|a, b| a.path.cmp(&b.path));
+    remove_nested(&mut matches);
+    Ok(matches)
+}
+
+/// Scan `root` for folders matching the configured clean rules.
+///
+/// Like `scan_matches_with`, without a callback for each match as it is found.
+pub fn scan_matches(root: &Path, options: &CleanOptions) -> Result<Vec<CleanMatch>, CleanError> {
+    scan_matches_with(root, options, |_| {})
+}
+
+/// Scan `root` for folders matching the configured clean rules, handing each match to
+/// `on_match` as soon as it is found.
+///
+/// Folders that match a rule but are filtered out (excluded, too small, outside the age window)
+/// are returned too, with `skipped` set. Results are sorted by path.
+pub fn scan_matches_with(
+    root: &Path,
+    options: &CleanOptions,
+    on_match: impl Fn(&CleanMatch) + Sync,
+) -> Result<Vec<CleanMatch>, CleanError> {
+    scan_root(root, options, on_match, &AtomicU64::new(0))
+}
+
+/// `scan_matches_with`, adding each directory the walk visits to `visited`.
+fn scan_root(
+    root: &Path,
+    options: &CleanOptions,
+    on_match: impl Fn(&CleanMatch) + Sync,
+    visited: &AtomicU64,
+) -> Result<Vec<CleanMatch>, CleanError> {
+    if !root.exists() {
+        return Err(CleanError::NotFound(root.to_path_buf()));
+    }
+    if !root.is_dir() {
+        return Err(CleanError::NotADirectory(root.to_path_buf()));
+    }
+
+    // Together the two ages bound a window, which must leave some room
+    if let (Some(older), Some(newer)) = (options.older_than, options.newer_than)
+        && newer <= older
+    {
+        return Err(CleanError::EmptyAgeWindow { older, newer });
+    }
+
+    let rules = load_rules(root, options)?;
+    let names = rule_matchers(&rules)?;
+    let excludes = build_excludes(root, &options.exclude)?;
+    let protects = build_protects(root, &rules, &options.protect)?;
+
+    // Anything modified after this point in time is considered recent and left alone
+    let recent_cutoff = options
+        .older_than
+        .map(|days| SystemTime::now() - Duration::from_secs(days * SECS_PER_DAY));
+    // ...and anything last modified before this point is too old for --newer-than
+    let stale_cutoff = options
+        .newer_than
+        .map(|days| SystemTime::now() - Duration::from_secs(days * SECS_PER_DAY));
+
+    let cache = cache_usable(options).then(SizeCache::load);
+    let dirty = DirtyRepos::default();
+    // Only deleting needs to keep clear of running builds; a dry run reports everything
+    let delete = options.mode == Mode::Delete;
+    let matches = Mutex::new(Vec::new());
+    let reclaimable = AtomicU64::new(0);
+    let progress = if options.progress && io::stderr().is_terminal() {
+        let bar = ProgressBar::new_spinner();
+        bar.enable_steady_tick(Duration::from_millis(120));
+        bar
+    } else {
+        ProgressBar::hidden()
+    };
+
+    // Walk to collect matches (in parallel unless --sequential); deletion happens afterwards
+    // Ignore files are only consulted with --respect-gitignore, so by default nothing is
+    // hidden by git.
+    let visit = |entry: Result<ignore::DirEntry, ignore::Error>| -> WalkState {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(_) => return WalkState::Continue,
+        };
+
+        // A root of `.` or `..` has no name of its own; resolve it so the root itself
+        // can match a rule, with its real parent checked for the indicator
+        let resolved;
+        let mut path = entry.path();
+        if entry.depth() == 0
+            && path.file_name().is_none()
+            && let Ok(canonical) = path.canonicalize()
+        {
+            resolved = canonical;
+            path = &resolved;
+        }
+
+        if path.is_dir() {
+            visited.fetch_add(1, Ordering::Relaxed);
+            let folder_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+            for (rule, name) in rules.iter().zip(&names) {
+                if name.is_match(folder_name) {
+                    let found = |size_bytes, skipped| CleanMatch {
+                        path: path.to_path_buf(),
+                        rule: rule.folder_name.clone(),
+                        category: rule.category,
+                        description: rule.description.clone(),
+                        size_bytes,
+                        size_timed_out: false,
+                        skipped,
+                    };
+
+                    // Like a .gitignore, excluding a directory covers everything inside it
+                    let is_excluded = |excludes: &Gitignore| {
+                        path.starts_with(root)
+                            && excludes.matched_path_or_any_parents(path, true).is_ignore()
+                    };
+                    let excluded = is_excluded(&excludes)
+                        || protects.get(&rule.folder_name).is_some_and(is_excluded);
+                    if excluded {
+                        matches
+                            .lock()
+                            .unwrap()
+                            .push(found(0, Some(SkipReason::Protected)));
+                        return WalkState::Skip;
+                    }
+
+                    let is_symlink = entry.path_is_symlink();
+                    if is_symlink && !options.follow_symlinks {
+                        matches
+                            .lock()
+                            .unwrap()
+                            .push(found(0, Some(SkipReason::Symlink)));
+                        return WalkState::Skip;
+                    }
+
+                    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+                    // We need to check if indicator exists.
+                    // Since we are inside a parallel walker, simple exists() check is fine,
+                    // but we should avoid expensive ops if possible.
+                    // matches_indicator is reasonably fast (stat check).
+                    let should_clean = (options.ignore_indicator
+                        || find_indicator(
+                            root,
+                            parent,
+                            &rule.project_indicators,
+                            if rule.indicator_in_parent {
+                                0
+                            } else {
+                                options.indicator_search_depth
+                            },
+                        ))
+                        && matches_indicators(path, &rule.contents);
+
+                    if should_clean {
+                        // Calculate size (and age) before deleting (or just for reporting)
+                        progress.set_message(format!(
+                            "Sizing {} | {} reclaimable",
+                            path.display(),
+                            ByteSize(reclaimable.load(Ordering::Relaxed))
+                        ));
+                        // A symlinked folder only ever has its link removed, which frees nothing
+                        let cached = cache.as_ref().and_then(|c| c.get(path, options.on_disk));
+                        let stats = if is_symlink {
+                            Some(FolderStats {
+                                size: 0,
+                                newest_mtime: None,
+                            })
+                        } else if let Some(size) = cached {
+                            // Only used when nothing below depends on the newest mtime
+                            Some(FolderStats {
+                                size,
+                                newest_mtime: None,
+                            })
+                        } else {
+                            let stats =
+                                stats_with_timeout(path, options.on_disk, options.size_timeout);
+                            if let (Some(cache), Some(stats)) = (&cache, &stats) {
+                                cache.insert(path, options.on_disk, stats.size);
+                            }
+                            stats
+                        };
+                        let timed_out = stats.is_none();
+                        let stats = stats.unwrap_or(FolderStats {
+                            size: 0,
+                            newest_mtime: None,
+                        });
+                        let size = stats.size;
+
+                        // Without stats no filter can vouch for the folder, so it only
+                        // passes when nothing would have been checked anyway
+                        let needs_stats = delete
+                            || options.min_size.is_some()
+                            || options.older_than.is_some()
+                            || options.newer_than.is_some();
+                        let skipped = match (recent_cutoff, stats.newest_mtime) {
+                            _ if timed_out && needs_stats => Some(SkipReason::SizeUnknown),
+                            _ if !timed_out
+                                && options.min_size.is_some_and(|min| size < min.as_u64()) =>
+                            {
+                                Some(SkipReason::TooSmall)
+                            }
+                            (Some(cutoff), Some(mtime)) if mtime > cutoff => {
+                                Some(SkipReason::Recent {
+                                    days: days_since(mtime),
+                                })
+                            }
+                            (_, Some(mtime)) if stale_cutoff.is_some_and(|c| mtime < c) => {
+                                Some(SkipReason::Stale {
+                                    days: days_since(mtime),
+                                })
+                            }
+                            _ if options.skip_dirty && dirty.contains(path) => {
+                                Some(SkipReason::Dirty)
+                            }
+                            _ if delete
+                                && !is_symlink
+                                && !options.force_busy
+                                && looks_busy(path, stats.newest_mtime) =>
+                            {
+                                Some(SkipReason::Busy)
+                            }
+                            _ => None,
+                        };
+
+                        if skipped.is_none() {
+                            reclaimable.fetch_add(size, Ordering::Relaxed);
+                        }
+                        let m = CleanMatch {
+                            size_timed_out: timed_out,
+                            ..found(size, skipped)
+                        };
+                        on_match(&m);
+                        matches.lock().unwrap().push(m);
+
+                        return WalkState::Skip; // Don't scan inside the folder we just found
+                    }
+                }
+            }
+        }
+        WalkState::Continue
+    };
+
+    // Hidden entries are skipped unless --hidden is given, except for dot-named rule
+    // folders such as `.venv`, `.gradle` or `.next`, which are always reachable.
+    let show_hidden = options.hidden;
+    let mut dot_rules = GlobSetBuilder::new();
+    for (rule, name) in rules.iter().zip(&names) {
+        if rule.folder_name.starts_with('.') {
+            dot_rules.add(name.glob().clone());
+        }
+    }
+    let dot_rules = dot_rules
+        .build()
+        .map_err(|e| CleanError::InvalidPattern(e.to_string()))?;
+    let visible = move |e: &ignore::DirEntry| {
+        let name = e.file_name().to_string_lossy();
+        show_hidden || e.depth() == 0 || !name.starts_with('.') || dot_rules.is_match(&*name)
+    };
+
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .threads(options.threads.unwrap_or_else(num_cpus::get))
+        .max_depth(options.max_depth)
+        .hidden(false)
+        .parents(options.parents)
+        .ignore(options.respect_gitignore)
+        .git_ignore(options.respect_gitignore)
+        .git_global(options.respect_gitignore)
+        .git_exclude(options.respect_gitignore);
+
+    if options.sequential {
+        // Sorted single-threaded walk, so matches are found in the same order on every run.
+        // The sequential iterator has no WalkState, so children of a skipped folder are
+        // filtered out as they are read instead.
+        let skipped_dir: Arc<Mutex<Option<PathBuf>>> = Arc::default();
+        let last_skipped = Arc::clone(&skipped_dir);
+        builder
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .filter_entry(move |e| {
+                visible(e) && last_skipped.lock().unwrap().as_deref() != e.path().parent()
+            });
+        for entry in builder.build() {
+            let path = entry.as_ref().ok().map(|e| e.path().to_path_buf());
+            if matches!(visit(entry), WalkState::Skip) {
+                *skipped_dir.lock().unwrap() = path;
+            }
+        }
+    } else {
+        builder.filter_entry(visible).build_parallel().run(|| {
+            let visit = &visit;
+            Box::new(visit)
+        });
+    }
+
+    progress.finish_and_clear();
+
+    if let Some(cache) = &cache
+        && let Err(e) = cache.save()
+    {
+        term::warning(e);
+    }
+
+    let mut matches = matches.into_inner().unwrap();
+    matches.sort_by(|a, b| a.path.cmp(&b.path));
+    remove_nested(&mut matches);
+    Ok(matches)
+}
+
+/// Drop matches that live inside another match so their size isn't counted twice.
+///
+/// Expects `matches` sorted by path: component-wise ordering puts every descendant
+/// directly after its ancestor, so comparing against the last kept match is enough.
+fn remove_nested(matches: &mut Vec<CleanMatch>) {
+    let mut kept: Vec<CleanMatch> = Vec::with_capacity(matches.len());
+    for m in matches.drain(..) {
+        if kept.last().is_some_and(|k| m.path.starts_with(&k.path)) {
+            continue;
+        }
+        kept.push(m);
+    }
+    *matches = kept;
+}
+
+/// Remove `path` entry by entry, returning the first child that could not be removed.
+fn remove_tree(path: &Path) -> Result<(), (PathBuf, io::Error)> {
+    let entries = fs::read_dir(path).map_err(|e| (path.to_path_buf(), e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| (path.to_path_buf(), e))?;
+        let child = entry.path();
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+        if is_dir {
+            remove_tree(&child)?;
+        } else {
+            fs::remove_file(&child).map_err(|e| (child, e))?;
+        }
+    }
+    fs::remove_dir(path).map_err(|e| (path.to_path_buf(), e))
+}
+
+/// `remove_dir_all` doesn't say which entry failed, so retry by hand to pinpoint it.
+fn locate_failure(path: &Path, error: io::Error) -> io::Error {
+    match remove_tree(path) {
+        Err((child, e)) if child != path => {
+            let e = explain_locked(e);
+            io::Error::new(e.kind(), format!("{} (at {:?})", e, child))
+        }
+        _ => explain_locked(error),
+    }
+}
+
+/// Whether an OS error code means a file is open or locked by another process.
+///
+/// Windows won't remove a file another process has open, and one such file fails the
+/// whole folder. Unix unlinks open files, so there it only covers busy mount points
+/// (`EBUSY`) and running executables on systems that refuse to touch them (`ETXTBSY`).
+#[cfg(windows)]
+fn is_locked(code: i32) -> bool {
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    const ERROR_LOCK_VIOLATION: i32 = 33;
+    // The shell API behind the Recycle Bin wraps Win32 errors in an HRESULT
+    let code = if code as u32 & 0xFFFF_0000 == 0x8007_0000 {
+        code & 0xFFFF
+    } else {
+        code
+    };
+    matches!(code, ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION)
+}
+
+#[cfg(not(windows))]
+fn is_locked(code: i32) -> bool {
+    matches!(
+        io::Error::from_raw_os_error(code).kind(),
+        io::ErrorKind::ResourceBusy | io::ErrorKind::ExecutableFileBusy
+    )
+}
+
+/// Say so when a delete failed because a file is in use, since the OS message rarely does.
+fn explain_locked(error: io::Error) -> io::Error {
+    match error.raw_os_error() {
+        Some(code) if is_locked(code) => {
+            io::Error::new(error.kind(), format!("{}{}", error, LOCKED_HINT))
+        }
+        _ => error,
+    }
+}
+
+/// A failed move to the trash as an `io::Error`, explaining locked files like a delete would.
+fn trash_error(error: trash::Error) -> io::Error {
+    match &error {
+        trash::Error::Os { code, .. } if is_locked(*code) => {
+            io::Error::other(format!("{}{}", error, LOCKED_HINT))
+        }
+        _ => io::Error::other(error),
+    }
+}
+
+/// Delete every match that wasn't skipped, in parallel.
+///
+/// With `trash`, folders are moved to the system trash (the Recycle Bin on Windows, the
+/// Finder or freedesktop.org Trash elsewhere) instead of being removed.
+pub fn delete_matches(matches: &[CleanMatch], trash: bool) -> DeleteReport {
+    delete_matches_with(matches, trash, &ProgressBar::hidden())
+}
+
+/// Like [`delete_matches`], advancing `progress` by each folder's size as it goes.
+fn delete_matches_with(
+    matches: &[CleanMatch],
+    trash: bool,
+    progress: &ProgressBar,
+) -> DeleteReport {
+    matches
+        .par_iter()
+        .filter(|m| m.skipped.is_none())
+        .map(|m| {
+            progress.set_message(m.path.display().to_string());
+            let mut report = DeleteReport::default();
+            let is_symlink = fs::symlink_metadata(&m.path).is_ok_and(|md| md.is_symlink());
+            let result = if is_symlink {
+                // Never delete through a link: only the link itself goes
+                fs::remove_file(&m.path).or_else(|_| fs::remove_dir(&m.path))
+            } else if trash {
+                // The trash records the path it was given, which the manifest is matched on
+                let target = m.path.canonicalize().unwrap_or_else(|_| m.path.clone());
+                trash::delete(&target).map_err(trash_error)
+            } else {
+                fs::remove_dir_all(&m.path).map_err(|e| locate_failure(&m.path, e))
+            };
+            match result {
+                Ok(_) => {
+                    report.deleted.push(m.path.clone());
+                    report.freed_bytes = m.size_bytes;
+                }
+                Err(e) => report.failed.push((m.path.clone(), e)),
+            }
+            progress.inc(m.size_bytes);
+            report
+        })
+        .reduce(DeleteReport::default, |mut a, b| {
+            a.merge(b);
+            a
+        })
+}
+
+/// Remove the directories that deleting each folder left empty, walking up until one
+/// still has contents. Never removes a scan root or the home directory.
+fn prune_empty_parents(deleted: &[PathBuf], roots: &[PathBuf]) -> u64 {
+    let home = dirs::home_dir();
+    let mut pruned = 0;
+    for path in deleted {
+        let Some(root) = roots.iter().find(|root| path.starts_with(root)) else {
+            continue;
+        };
+        for dir in path.ancestors().skip(1) {
+            if dir == root.as_path() || !dir.starts_with(root) || home.as_deref() == Some(dir) {
+                break;
+            }
+            // Only succeeds on an empty directory, so there is no check-then-remove race
+            if fs::remove_dir(dir).is_err() {
+                break;
+            }
+            pruned += 1;
+        }
+    }
+    pruned
+}
+
+/// Append a line to the audit log for every folder deletion was attempted on.
+fn write_log(
+    file: &mut fs::File,
+    active: &[&CleanMatch],
+    report: &DeleteReport,
+    trash: bool,
+) -> io::Result<()> {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let errors: HashMap<&Path, &io::Error> = report
+        .failed
+        .iter()
+        .map(|(path, e)| (path.as_path(), e))
+        .collect();
+    // Write everything at once so lines from concurrent runs don't interleave
+    let mut out = Vec::new();
+    for m in active {
+        let error = errors.get(m.path.as_path());
+        if error.is_none() && !report.deleted.contains(&m.path) {
+            continue; // Never attempted: declined, aborted or past the budget
+        }
+        let entry = LogEntry {
+            time,
+            path: &m.path,
+            rule: &m.rule,
+            size_bytes: m.size_bytes,
+            trash,
+            deleted: error.is_none(),
+            error: error.map(|e| e.to_string()),
+        };
+        serde_json::to_writer(&mut out, &entry)?;
+        out.push(b'\n');
+    }
+    file.write_all(&out)
+}
+
+/// Bar for the deletion phase; sizes were all measured during the scan, so the total is
+/// known upfront and indicatif can derive the rate and ETA from it.
+fn deletion_progress(total: u64, options: &CleanOptions) -> ProgressBar {
+    // Prompts and a redrawing bar would fight over the terminal
+    if !options.progress || options.interactive || !io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    let template = if options.eta {
+        "{spinner} [{bar:30}] {bytes}/{total_bytes} at {bytes_per_sec}, ETA {eta} | {wide_msg}"
+    } else {
+        "{spinner} {bytes}/{total_bytes} | {wide_msg}"
+    };
+    let style =
+        ProgressStyle::with_template(template).unwrap_or_else(|_| ProgressStyle::default_bar());
+    let bar = ProgressBar::new(total).with_style(style);
+    bar.enable_steady_tick(Duration::from_millis(120));
+    bar
+}
+
+fn print_deleting(m: &CleanMatch, trash: bool) {
+    say!(
+        "🗑️  {} {:?} ({}) - freeing {}...",
+        if trash { "Trashing" } else { "Deleting" },
+        m.path,
+        m.description,
+        size_label(m)
+    );
+}
+
+/// Why deleting under `root` is probably a mistake: it is the filesystem root, the home
+/// directory, or so large it can't be a folder of projects.
+pub fn broad_root(root: &Path) -> Option<String> {
+    let canonical = root.canonicalize().ok()?;
+    if canonical.parent().is_none() {
+        return Some("it is the filesystem root".to_string());
+    }
+    if dirs::home_dir().and_then(|home| home.canonicalize().ok()) == Some(canonical.clone()) {
+        return Some("it is your home directory".to_string());
+    }
+    let entries = fs::read_dir(&canonical).ok()?.count();
+    (entries > BROAD_ROOT_ENTRIES).then(|| format!("it has {} entries", entries))
+}
+
+pub fn clean_projects(roots: &[PathBuf], options: &CleanOptions) -> ExitCode {
+    let roots = dedup_roots(roots);
+    let delete = options.mode == Mode::Delete;
+
+    if delete && !options.allow_broad_root {
+        for root in &roots {
+            if let Some(reason) = broad_root(root) {
+                term::error(format!("Refusing to delete under {:?}: {}.", root, reason));
+                esay!(
+                    "Point --path at a folder of projects, or re-run with --i-know-what-im-doing."
+                );
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let human = options.format == OutputFormat::Human;
+    // Per-folder lines; the final summary is printed whenever output is human
+    let detail = human && options.verbosity != Verbosity::Quiet;
+    let verbose = human && options.verbosity == Verbosity::Verbose;
+
+    if options.ignore_indicator {
+        term::warning(
+            "Project indicator checks are disabled: every folder named like a rule will be matched.",
+        );
+    }
+    if options.cache && !cache_usable(options) {
+        term::warning(
+            "--cache is ignored with --delete, --older-than or --newer-than, which need fresh modification times.",
+        );
+    }
+
+    // Size calculation and deletion run on the rayon pool, so cap that too
+    if let Some(threads) = options.threads {
+        let _ = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global();
+    }
+
+    if detail {
+        for root in &roots {
+            say!("🔍 Scanning path: {:?}", root);
+        }
+        if !delete {
+            say!(
+                "{}",
+                term::paint(
+                    "⚠️  DRY RUN: No folders will be deleted. Use --delete to remove them.",
+                    Color::Yellow
+                )
+            );
+        } else if options.trash {
+            say!(
+                "{}",
+                term::paint(
+                    format!(
+                        "⚠️  TRASH MODE: Folders will be moved to the {}.",
+                        TRASH_NAME
+                    ),
+                    Color::Yellow
+                )
+            );
+        } else {
+            say!(
+                "{}",
+                term::paint(
+                    "⚠️  DELETING MODE: Folders will be permanently removed.",
+                    Color::Red
+                )
+            );
+        }
+        if let Some(depth) = options.max_depth {
+            say!("📏 Depth limit: {} level(s) below the root.", depth);
+        }
+        let configs: BTreeSet<PathBuf> = roots
+            .iter()
+            .filter_map(|root| config_path(root, options))
+            .collect();
+        for path in configs {
+            say!("📄 Rules config: {:?}", path);
+        }
+        say!();
+    }
+
+    let started = Instant::now();
+    let visited = AtomicU64::new(0);
+    let scanned = scan_roots_with(&roots, options, |_| {}, &visited);
+    let scan_time = started.elapsed();
+
+    let matches = match scanned {
+        Ok(matches) => matches,
+        Err(e) => {
+            term::error(&e);
+            if let CleanError::NotFound(_) = e {
+                esay!(
+                    "Hint: If you are on Windows, ensure you use forward slashes (/) or quote the path if it contains backslashes (\\)."
+                );
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut active: Vec<&CleanMatch> = matches.iter().filter(|m| m.skipped.is_none()).collect();
+
+    // With a budget, go largest first; a dry run previews just the folders it would take
+    let budget = options.free_up.map(|b| b.as_u64());
+    let mut untouched = 0;
+    if let Some(budget) = budget {
+        active.sort_by_key(|m| std::cmp::Reverse(m.size_bytes));
+        if !delete {
+            let take = budget_cutoff(&active, budget);
+            untouched = active.len() - take;
+            active.truncate(take);
+        }
+    }
+
+    if delete
+        && !options.interactive
+        && let Some(limit) = options.max_matches
+        && active.len() > limit
+    {
+        term::error(format!(
+            "Refusing to delete {} folders, more than --max-matches {}.",
+            active.len(),
+            limit
+        ));
+        esay!(
+            "Run a dry run to review them, then re-run with a higher --max-matches or with --yes."
+        );
+        return ExitCode::FAILURE;
+    }
+
+    if verbose && delete {
+        print_skipped(&matches, options);
+    } else if detail && delete {
+        for m in &matches {
+            let warning = match m.skipped {
+                Some(SkipReason::Symlink) => format!(
+                    "⚠️  Skipping symlinked {:?} (use --follow-symlinks to remove the link)",
+                    m.path
+                ),
+                Some(SkipReason::Busy) => format!(
+                    "⚠️  Skipping {:?}: it looks in use by a running build or install",
+                    m.path
+                ),
+                Some(SkipReason::SizeUnknown) => format!(
+                    "⚠️  Skipping {:?}: sizing timed out, so it can't be checked before deleting",
+                    m.path
+                ),
+                _ => continue,
+            };
+            esay!("{}", term::paint(warning, Color::Yellow));
+        }
+    }
+
+    if detail && !delete {
+        print_skipped(&matches, options);
+        print_top_matches(&active, options.top);
+    }
+
+    // Opened before anything is removed, so a bad path never leaves deletions unrecorded
+    let mut log = match &options.log {
+        Some(path) if delete => match fs::OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Some((path, file)),
+            Err(e) => {
+                term::error(format!("Could not open log {:?}: {}", path, e));
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => None,
+    };
+
+    let trash_before = if delete && options.trash {
+        manifest::trash_snapshot()
+    } else {
+        HashSet::new()
+    };
+    // Resolved while the folders still exist, to find them in the trash afterwards
+    let resolved: HashMap<&Path, PathBuf> = if delete && options.trash {
+        matches
+            .iter()
+            .filter(|m| m.skipped.is_none())
+            .filter_map(|m| Some((m.path.as_path(), m.path.canonicalize().ok()?)))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    // Filesystem free space shows the real effect, which can differ from summed file sizes.
+    // Roots may share a volume, so it is only reported for a single root.
+    let volume = match roots.as_slice() {
+        [root] if delete => Some(root.as_path()),
+        _ => None,
+    };
+    let free_before = volume.and_then(|root| fs2::available_space(root).ok());
+
+    let mut report = DeleteReport::default();
+    if delete && options.interactive {
+        // Prompt sequentially so stdin reads don't race
+        let (mut skipped, mut aborted) = (0u64, 0u64);
+        let mut yes_to_all = false;
+        for (i, m) in active.iter().enumerate() {
+            if !yes_to_all {
+                match prompt_delete(&m.path, m.size_bytes) {
+                    Answer::Yes => {}
+                    Answer::All => yes_to_all = true,
+                    Answer::No => {
+                        skipped += 1;
+                        continue;
+                    }
+                    Answer::Quit => {
+                        aborted = (active.len() - i) as u64;
+                        break;
+                    }
+                }
+            }
+
+            if detail {
+                print_deleting(m, options.trash);
+            }
+            report.merge(delete_matches(std::slice::from_ref(*m), options.trash));
+        }
+
+        if human {
+            say!(
+                "\n📋 Deleted: {}, Skipped: {}, Aborted: {}",
+                report.deleted.len(),
+                skipped,
+                aborted
+            );
+        }
+    } else if delete && let Some(budget) = budget {
+        let expected = active[..budget_cutoff(&active, budget)]
+            .iter()
+            .map(|m| m.size_bytes)
+            .sum();
+        let progress = deletion_progress(expected, options);
+        // Delete one at a time so a failed delete doesn't count towards the budget
+        for (i, m) in active.iter().enumerate() {
+            if report.freed_bytes >= budget {
+                untouched = active.len() - i;
+                break;
+            }
+            if detail {
+                progress.suspend(|| print_deleting(m, options.trash));
+            }
+            report.merge(delete_matches_with(
+                std::slice::from_ref(*m),
+                options.trash,
+                &progress,
+            ));
+        }
+        progress.finish_and_clear();
+    } else if delete {
+        if detail {
+            active.iter().for_each(|m| print_deleting(m, options.trash));
+        }
+        let progress = deletion_progress(active.iter().map(|m| m.size_bytes).sum(), options);
+        report = delete_matches_with(&matches, options.trash, &progress);
+        progress.finish_and_clear();
+    }
+
+    let pruned = if options.prune_empty_parents {
+        prune_empty_parents(&report.deleted, &roots)
+    } else {
+        0
+    };
+
+    if let Some((path, file)) = &mut log
+        && let Err(e) = write_log(file, &active, &report, options.trash)
+    {
+        term::warning(format!("Could not write log {:?}: {}", path, e));
+    }
+
+    if options.trash && !report.deleted.is_empty() {
+        let trashed: Vec<PathBuf> = report
+            .deleted
+            .iter()
+            .filter_map(|path| resolved.get(path.as_path()).cloned())
+            .collect();
+        match manifest::write_manifest(&trash_before, &trashed) {
+            Ok(path) if human => say!(
+                "🧾 Manifest written to {:?}. Run `rs-clean restore` to undo.",
+                path
+            ),
+            Ok(_) => {}
+            Err(e) => term::warning(e),
+        }
+    }
+
+    let exit_code = if report.failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    };
+
+    let count = active.len() as u64;
+    let bytes = if delete {
+        report.freed_bytes
+    } else {
+        active.iter().map(|m| m.size_bytes).sum()
+    };
+    let count_skipped = |f: fn(&SkipReason) -> bool| {
+        matches
+            .iter()
+            .filter(|m| m.skipped.as_ref().is_some_and(f))
+            .count() as u64
+    };
+    let recent = count_skipped(|r| matches!(r, SkipReason::Recent { .. }));
+    let stale = count_skipped(|r| matches!(r, SkipReason::Stale { .. }));
+    let small = count_skipped(|r| matches!(r, SkipReason::TooSmall));
+    let protected = count_skipped(|r| matches!(r, SkipReason::Protected));
+    let busy = count_skipped(|r| matches!(r, SkipReason::Busy));
+    let dirty = count_skipped(|r| matches!(r, SkipReason::Dirty));
+    let unknown = count_skipped(|r| matches!(r, SkipReason::SizeUnknown));
+    let small_bytes: u64 = matches
+        .iter()
+        .filter(|m| matches!(m.skipped, Some(SkipReason::TooSmall)))
+        .map(|m| m.size_bytes)
+        .sum();
+
+    let report = CleanReport {
+        summary: CleanSummary {
+            matched: count,
+            deleted: report.deleted.len() as u64,
+            failed: report.failed.len() as u64,
+            total_bytes: bytes,
+            dry_run: !delete,
+            skipped_recent: recent,
+            skipped_stale: stale,
+            skipped_small: small,
+            skipped_small_bytes: small_bytes,
+            skipped_busy: busy,
+            skipped_dirty: dirty,
+            skipped_unknown: unknown,
+            protected,
+            budget_bytes: budget,
+            untouched,
+            pruned_parents: pruned,
+            free_before_bytes: free_before,
+            free_after_bytes: volume
+                .filter(|_| free_before.is_some())
+                .and_then(|root| fs2::available_space(root).ok()),
+            // What was actually removed when deleting, what could be otherwise
+            by_rule: rule_totals(
+                active
+                    .iter()
+                    .filter(|m| !delete || report.deleted.contains(&m.path))
+                    .copied(),
+            ),
+            dirs_scanned: visited.into_inner(),
+            scan_seconds: scan_time.as_secs_f64(),
+        },
+        matches: active
+            .iter()
+            .map(|m| CleanResult {
+                matched: m,
+                deleted: report.deleted.contains(&m.path),
+            })
+            .collect(),
+        failures: report
+            .failed
+            .iter()
+            .map(|(path, e)| Failure {
+                path: path.clone(),
+                error: e.to_string(),
+            })
+            .collect(),
+    };
+
+    let formatter: Box<dyn Formatter> = match options.format {
+        OutputFormat::Human => Box::new(Human { options }),
+        OutputFormat::Json => Box::new(Json),
+        OutputFormat::Csv => Box::new(Csv),
+        OutputFormat::Table => Box::new(Table),
+        OutputFormat::Jsonl => Box::new(JsonLines),
+    };
+    let nothing_to_report = report.summary.matched == 0 && report.summary.failed == 0;
+    if !(options.silent_when_clean && nothing_to_report)
+        && let Err(e) = formatter.write_report(&mut io::stdout().lock(), &report)
+    {
+        term::error(format!("Could not write report: {}", e));
+        return ExitCode::FAILURE;
+    }
+
+    if let Some(path) = &options.report {
+        let written = fs::File::create(path).and_then(|file| {
+            let mut out = io::BufWriter::new(file);
+            Json.write_report(&mut out, &report)?;
+            out.flush()
+        });
+        if let Err(e) = written {
+            term::error(format!("Could not write report to {:?}: {}", path, e));
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(command) = &options.on_complete {
+        run_hook(command, &report.summary);
+    }
+
+    if !delete
+        && let Some(limit) = options.fail_if_over
+        && bytes > limit.as_u64()
+    {
+        return ExitCode::from(2);
+    }
+
+    exit_code
+}
+
+/// A duration in the largest whole unit, e.g. `6h` or `90s`.
+fn format_interval(interval: Duration) -> String {
+    let secs = interval.as_secs();
+    match secs {
+        _ if secs >= SECS_PER_DAY && secs.is_multiple_of(SECS_PER_DAY) => {
+            format!("{}d", secs / SECS_PER_DAY)
+        }
+        _ if secs >= 3600 && secs.is_multiple_of(3600) => format!("{}h", secs / 3600),
+        _ if secs >= 60 && secs.is_multiple_of(60) => format!("{}m", secs / 60),
+        _ => format!("{}s", secs),
+    }
+}
+
+/// Run `clean_projects` every `interval` until SIGINT or SIGTERM.
+///
+/// A signal during a run lets it finish before exiting, so no folder is left half
+/// deleted; a second Ctrl+C exits right away.
+pub fn run_daemon(roots: &[PathBuf], options: &CleanOptions, interval: Duration) -> ExitCode {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+
+    let stop = Arc::new(AtomicBool::new(false));
+    for signal in [SIGINT, SIGTERM] {
+        // Registered first, so it only fires once `stop` has already been set
+        let registered =
+            signal_hook::flag::register_conditional_shutdown(signal, 130, Arc::clone(&stop))
+                .and_then(|_| signal_hook::flag::register(signal, Arc::clone(&stop)));
+        if let Err(e) = registered {
+            term::error(format!("Could not install signal handler: {}", e));
+            return ExitCode::FAILURE;
+        }
+    }
+
+    say!(
+        "🔁 Daemon mode: cleaning every {} (Ctrl+C to stop).",
+        format_interval(interval)
+    );
+    for run in 1.. {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        say!("\n🕒 Run {} started at {} (Unix time)", run, started);
+        if clean_projects(roots, options) != ExitCode::SUCCESS {
+            term::warning(format!("Run {} did not complete cleanly.", run));
+        }
+
+        // Sleep in short steps so a signal is noticed promptly
+        let next = Instant::now() + interval;
+        while !stop.load(Ordering::Relaxed) && Instant::now() < next {
+            thread::sleep(DAEMON_POLL.min(next.saturating_duration_since(Instant::now())));
+        }
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+    say!("👋 Daemon stopped.");
+    ExitCode::SUCCESS
+}
+
+/// Folders a scan would clean and their total size.
+fn reclaimable(matches: &[CleanMatch]) -> (usize, u64) {
+    let active = matches.iter().filter(|m| m.skipped.is_none());
+    active.fold((0, 0), |(count, bytes), m| {
+        (count + 1, bytes + m.size_bytes)
+    })
+}
+
+/// Scan once, then rescan whenever something under the roots changes and report when
+/// the reclaimable space moves. Never deletes anything; runs until interrupted.
+pub fn watch_projects(roots: &[PathBuf], options: &CleanOptions) -> ExitCode {
+    let roots = dedup_roots(roots);
+    for root in &roots {
+        say!("🔍 Scanning path: {:?}", root);
+    }
+    let mut last = match scan_roots(&roots, options) {
+        Ok(matches) => {
+            let active: Vec<&CleanMatch> = matches.iter().filter(|m| m.skipped.is_none()).collect();
+            print_top_matches(&active, options.top);
+            reclaimable(&matches)
+        }
+        Err(e) => {
+            term::error(&e);
+            return ExitCode::FAILURE;
+        }
+    };
+    say!(
+        "\n💡 {} folder(s), {} reclaimable",
+        last.0,
+        ByteSize(last.1)
+    );
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(tx) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            term::error(format!("Could not start watching: {}", e));
+            return ExitCode::FAILURE;
+        }
+    };
+    for root in &roots {
+        if let Err(e) = notify::Watcher::watch(&mut watcher, root, notify::RecursiveMode::Recursive)
+        {
+            term::error(format!("Could not watch {:?}: {}", root, e));
+            return ExitCode::FAILURE;
+        }
+    }
+    say!("👀 Watching for changes (Ctrl+C to stop)...");
+
+    // Wait for the first event, then for a quiet spell, so a build rescans once
+    while rx.recv().is_ok() {
+        while rx.recv_timeout(WATCH_DEBOUNCE).is_ok() {}
+
+        match scan_roots(&roots, options) {
+            Ok(matches) => {
+                let (count, bytes) = reclaimable(&matches);
+                if (count, bytes) != last {
+                    let change = if bytes >= last.1 {
+                        format!("+{}", ByteSize(bytes - last.1))
+                    } else {
+                        format!("-{}", ByteSize(last.1 - bytes))
+                    };
+                    say!(
+                        "🔁 {} folder(s), {} reclaimable ({})",
+                        count,
+                        ByteSize(bytes),
+                        change
+                    );
+                    last = (count, bytes);
+                }
+            }
+            Err(e) => term::error(&e),
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp dir, removed again when dropped.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Scratch {
+            let dir =
+                std::env::temp_dir().join(format!("rs-clean-test-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Scratch(dir)
+        }
+
+        /// Create the file `rel` (and its parents) with a little content.
+        fn file(&self, rel: &str) -> PathBuf {
+            let path = self.0.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "content").unwrap();
+            path
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Paths (relative to `root`) of the matches a scan would clean.
+    fn cleanable(root: &Path, options: &CleanOptions) -> Vec<PathBuf> {
+        scan_matches(root, options)
+            .unwrap()
+            .into_iter()
+            .filter(|m| m.skipped.is_none())
+            .map(|m| m.path.strip_prefix(root).unwrap().to_path_buf())
+            .collect()
+    }
+
+    /// A cleanable `target` match at `path`, as if the scan had sized it.
+    fn target_at(path: &str, size_bytes: u64) -> CleanMatch {
+        CleanMatch {
+            path: PathBuf::from(path),
+            rule: "target".to_string(),
+            category: Category::BuildArtifacts,
+            description: "Rust build artifacts".to_string(),
+            size_bytes,
+            size_timed_out: false,
+            skipped: None,
+        }
+    }
+
+    /// The report of a dry run that found `matches`.
+    fn report(matches: &[CleanMatch]) -> CleanReport<'_> {
+        CleanReport {
+            matches: matches
+                .iter()
+                .map(|m| CleanResult {
+                    matched: m,
+                    deleted: false,
+                })
+                .collect(),
+            summary: CleanSummary {
+                matched: matches.len() as u64,
+                dry_run: true,
+                ..CleanSummary::default()
+            },
+            failures: Vec::new(),
+        }
+    }
+
+    fn rendered(formatter: &dyn Formatter, report: &CleanReport) -> String {
+        let mut out = Vec::new();
+        formatter.write_report(&mut out, report).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn dotnet_only() -> CleanOptions {
+        CleanOptions {
+            only: vec!["bin".to_string(), "obj".to_string()],
+            ..CleanOptions::default()
+        }
+    }
+
+    #[test]
+    fn dotnet_bin_with_build_output_is_matched() {
+        let tree = Scratch::new("dotnet-output");
+        tree.file("app/App.csproj");
+        tree.file("app/bin/Debug/net8.0/App.dll");
+        tree.file("app/obj/Release/App.dll");
+
+        assert_eq!(
+            cleanable(&tree.0, &dotnet_only()),
+            [PathBuf::from("app/bin"), PathBuf::from("app/obj")]
+        );
+    }
+
+    #[test]
+    fn dotnet_bin_of_scripts_is_not_matched() {
+        let tree = Scratch::new("dotnet-scripts");
+        tree.file("app/App.csproj");
+        tree.file("app/bin/deploy.sh");
+
+        assert!(cleanable(&tree.0, &dotnet_only()).is_empty());
+    }
+
+    #[test]
+    fn dotnet_project_must_be_the_immediate_parent() {
+        let tree = Scratch::new("dotnet-depth");
+        tree.file("app/App.csproj");
+        tree.file("app/tools/bin/Debug/Tool.dll");
+        let options = CleanOptions {
+            indicator_search_depth: 3,
+            ..dotnet_only()
+        };
+
+        assert!(cleanable(&tree.0, &options).is_empty());
+    }
+
+    #[test]
+    fn nested_matches_keep_only_the_outer_folder() {
+        let tree = Scratch::new("nested");
+        tree.file("crate/Cargo.toml");
+        tree.file("crate/target/debug/deps/inner/Cargo.toml");
+        tree.file("crate/target/debug/deps/inner/target/out.o");
+        tree.file("other/Cargo.toml");
+        tree.file("other/target/out.o");
+
+        // The inner crate as a root of its own finds its target again under crate/target
+        let roots = [tree.0.clone(), tree.0.join("crate/target/debug/deps/inner")];
+        let options = CleanOptions {
+            only: vec!["target".to_string()],
+            ..CleanOptions::default()
+        };
+        let matches = scan_roots(&roots, &options).unwrap();
+
+        let kept: Vec<PathBuf> = matches.into_iter().map(|m| m.path).collect();
+        assert_eq!(
+            kept,
+            [tree.0.join("crate/target"), tree.0.join("other/target")]
+        );
+    }
+
+    /// `app/node_modules` as a link to a real folder outside the project.
+    #[cfg(unix)]
+    fn linked_node_modules(tree: &Scratch) -> (PathBuf, PathBuf) {
+        tree.file("app/package.json");
+        let target = tree.file("shared/modules/left-pad/index.js");
+        let target = target.parent().unwrap().parent().unwrap().to_path_buf();
+        let link = tree.0.join("app/node_modules");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        (link, target)
+    }
+
+    fn node_modules_only() -> CleanOptions {
+        CleanOptions {
+            only: vec!["node_modules".to_string()],
+            ..CleanOptions::default()
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_match_is_skipped_by_default() {
+        let tree = Scratch::new("symlink-skip");
+        let (link, _) = linked_node_modules(&tree);
+
+        let matches = scan_matches(&tree.0, &node_modules_only()).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path, link);
+        assert!(matches!(matches[0].skipped, Some(SkipReason::Symlink)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follow_symlinks_removes_only_the_link() {
+        let tree = Scratch::new("symlink-delete");
+        let (link, target) = linked_node_modules(&tree);
+        let options = CleanOptions {
+            follow_symlinks: true,
+            ..node_modules_only()
+        };
+
+        let matches = scan_matches(&tree.0, &options).unwrap();
+        assert_eq!(
+            cleanable(&tree.0, &options),
+            [PathBuf::from("app/node_modules")]
+        );
+        // Removing the link frees nothing, so it isn't sized through the link
+        assert_eq!(matches[0].size_bytes, 0);
+
+        let report = delete_matches(&matches, false);
+        assert!(report.failed.is_empty());
+        assert!(fs::symlink_metadata(&link).is_err());
+        assert!(target.join("left-pad/index.js").is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sizing_does_not_follow_links_inside_a_folder() {
+        let tree = Scratch::new("symlink-size");
+        let own = tree.file("app/node_modules/own.js");
+        let shared = tree.file("shared/big.bin");
+        fs::write(&shared, vec![0u8; 64 * 1024]).unwrap();
+        std::os::unix::fs::symlink(
+            shared.parent().unwrap(),
+            tree.0.join("app/node_modules/link"),
+        )
+        .unwrap();
+
+        let stats = calculate_stats(own.parent().unwrap(), false);
+        assert!(
+            stats.size < 64 * 1024,
+            "sized through the link: {}",
+            stats.size
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn sharing_violations_are_locked() {
+        // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION, and the first as an HRESULT
+        assert!(is_locked(32));
+        assert!(is_locked(33));
+        assert!(is_locked(0x8007_0020_u32 as i32));
+        // ERROR_ACCESS_DENIED and ERROR_FILE_NOT_FOUND are not about locks
+        assert!(!is_locked(5));
+        assert!(!is_locked(2));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn busy_errors_are_locked() {
+        const EBUSY: i32 = 16;
+        const ETXTBSY: i32 = 26;
+        const ENOENT: i32 = 2;
+        assert!(is_locked(EBUSY));
+        assert!(is_locked(ETXTBSY));
+        assert!(!is_locked(ENOENT));
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn locked_errors_carry_the_hint() {
+        #[cfg(windows)]
+        let (locked, other) = (32, 5);
+        #[cfg(unix)]
+        let (locked, other) = (16, 2);
+
+        let explained = explain_locked(io::Error::from_raw_os_error(locked));
+        assert!(explained.to_string().ends_with(LOCKED_HINT));
+        let explained = explain_locked(io::Error::from_raw_os_error(other));
+        assert!(!explained.to_string().contains(LOCKED_HINT));
+
+        let trashed = trash_error(trash::Error::Os {
+            code: locked,
+            description: "in use".to_string(),
+        });
+        assert!(trashed.to_string().ends_with(LOCKED_HINT));
+        let trashed = trash_error(trash::Error::Os {
+            code: other,
+            description: "other".to_string(),
+        });
+        assert!(!trashed.to_string().contains(LOCKED_HINT));
+    }
+
+    #[test]
+    fn root_that_is_itself_a_match_is_cleaned() {
+        let tree = Scratch::new("root-match");
+        tree.file("app/package.json");
+        tree.file("app/node_modules/left-pad/index.js");
+        let modules = tree.0.join("app/node_modules");
+
+        let matches = scan_matches(&modules, &node_modules_only()).unwrap();
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path, modules);
+        assert_eq!(matches[0].rule, "node_modules");
+        assert!(matches[0].skipped.is_none());
+    }
+
+    #[test]
+    fn timed_out_sizing_never_passes_the_filters() {
+        let tree = Scratch::new("size-timeout");
+        tree.file("app/Cargo.toml");
+        tree.file("app/target/debug/app");
+        let options = CleanOptions {
+            only: vec!["target".to_string()],
+            size_timeout: Some(Duration::ZERO),
+            ..CleanOptions::default()
+        };
+
+        // Written just now, but without sizing the age can't be known
+        let aged = CleanOptions {
+            older_than: Some(30),
+            ..options
+        };
+        let matches = scan_matches(&tree.0, &aged).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert!(matches[0].size_timed_out);
+        assert!(matches!(matches[0].skipped, Some(SkipReason::SizeUnknown)));
+
+        let deleting = CleanOptions {
+            mode: Mode::Delete,
+            force_busy: true,
+            only: vec!["target".to_string()],
+            size_timeout: Some(Duration::ZERO),
+            ..CleanOptions::default()
+        };
+        assert!(cleanable(&tree.0, &deleting).is_empty());
+
+        // A plain dry run has nothing to check, so it still lists the folder
+        let plain = CleanOptions {
+            only: vec!["target".to_string()],
+            size_timeout: Some(Duration::ZERO),
+            ..CleanOptions::default()
+        };
+        assert_eq!(cleanable(&tree.0, &plain), [PathBuf::from("app/target")]);
+    }
+
+    #[test]
+    fn jsonl_reports_the_same_matches_as_json() {
+        let tree = Scratch::new("jsonl");
+        tree.file("app/Cargo.toml");
+        tree.file("app/target/debug/app");
+        tree.file("app/target/debug/deps/inner/Cargo.toml");
+        tree.file("app/target/debug/deps/inner/target/out.o");
+        tree.file("kept/Cargo.toml");
+        tree.file("kept/target/out.o");
+        let options = CleanOptions {
+            only: vec!["target".to_string()],
+            protect: vec![RuleProtect {
+                rule: "target".to_string(),
+                pattern: "kept/".to_string(),
+            }],
+            ..CleanOptions::default()
+        };
+
+        // Overlapping roots find the inner target too; the protected one is skipped
+        let roots = [tree.0.clone(), tree.0.join("app/target/debug/deps/inner")];
+        let matches: Vec<CleanMatch> = scan_roots(&roots, &options)
+            .unwrap()
+            .into_iter()
+            .filter(|m| m.skipped.is_none())
+            .collect();
+        let report = report(&matches);
+
+        let json: serde_json::Value = serde_json::from_str(&rendered(&Json, &report)).unwrap();
+        let mut lines: Vec<serde_json::Value> = rendered(&JsonLines, &report)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let last = lines.pop().unwrap();
+
+        assert_eq!(lines.len(), 1);
+        assert_eq!(
+            lines[0]["path"],
+            tree.0.join("app/target").to_str().unwrap()
+        );
+        assert_eq!(serde_json::Value::Array(lines), json["matches"]);
+        assert_eq!(last["summary"], json["summary"]);
+    }
+
+    #[test]
+    fn csv_quotes_fields_with_commas_quotes_and_newlines() {
+        let matches = [
+            target_at("plain/target", 42),
+            target_at("a,b/target", 42),
+            CleanMatch {
+                description: "Build output, Rust".to_string(),
+                ..target_at("say \"hi\"/target", 42)
+            },
+            target_at("two\nlines/target", 42),
+        ];
+
+        assert_eq!(
+            rendered(&Csv, &report(&matches)),
+            "path,rule,description,size_bytes,deleted\n\
+             plain/target,target,Rust build artifacts,42,false\n\
+             \"a,b/target\",target,Rust build artifacts,42,false\n\
+             \"say \"\"hi\"\"/target\",target,\"Build output, Rust\",42,false\n\
+             \"two\nlines/target\",target,Rust build artifacts,42,false\n"
+        );
+    }
+
+    #[test]
+    fn later_exclude_patterns_negate_earlier_ones() {
+        let tree = Scratch::new("exclude-negation");
+        for project in ["web", "important", "nested/important"] {
+            tree.file(&format!("{}/package.json", project));
+            tree.file(&format!("{}/node_modules/left-pad/index.js", project));
+        }
+        let options = CleanOptions {
+            exclude: vec![
+                "**/node_modules".to_string(),
+                "!important/node_modules".to_string(),
+            ],
+            ..node_modules_only()
+        };
+
+        assert_eq!(
+            cleanable(&tree.0, &options),
+            [PathBuf::from("important/node_modules")]
+        );
+
+        // The other way round, the exclude has the last word again
+        let reversed = CleanOptions {
+            exclude: options.exclude.iter().rev().cloned().collect(),
+            ..node_modules_only()
+        };
+        assert!(cleanable(&tree.0, &reversed).is_empty());
+    }
+
+    #[test]
+    fn protect_only_spares_its_own_rule() {
+        let tree = Scratch::new("protect");
+        for project in ["important", "scratch"] {
+            tree.file(&format!("{}/Cargo.toml", project));
+            tree.file(&format!("{}/package.json", project));
+            tree.file(&format!("{}/target/debug/app", project));
+            tree.file(&format!("{}/node_modules/left-pad/index.js", project));
+        }
+        let options = CleanOptions {
+            only: vec!["target".to_string(), "node_modules".to_string()],
+            protect: vec![RuleProtect {
+                rule: "target".to_string(),
+                pattern: "important/".to_string(),
+            }],
+            ..CleanOptions::default()
+        };
+
+        assert_eq!(
+            cleanable(&tree.0, &options),
+            [
+                PathBuf::from("important/node_modules"),
+                PathBuf::from("scratch/node_modules"),
+                PathBuf::from("scratch/target"),
+            ]
+        );
+    }
+
+    #[test]
+    fn protect_rejects_unknown_rules() {
+        let tree = Scratch::new("protect-unknown");
+        let options = CleanOptions {
+            protect: vec![RuleProtect {
+                rule: "targte".to_string(),
+                pattern: "important/".to_string(),
+            }],
+            ..CleanOptions::default()
+        };
+
+        match scan_matches(&tree.0, &options) {
+            Err(CleanError::UnknownRules { unknown, valid }) => {
+                assert_eq!(unknown, ["targte"]);
+                assert!(valid.iter().any(|rule| rule == "target"));
+            }
+            _ => panic!("expected an unknown rule error"),
+        }
+    }
+
+    #[test]
+    fn free_up_takes_the_largest_folders_until_the_budget_is_met() {
+        let matches = [
+            target_at("a/target", 500),
+            target_at("b/target", 300),
+            target_at("c/target", 200),
+        ];
+        let sorted: Vec<&CleanMatch> = matches.iter().collect();
+
+        assert_eq!(budget_cutoff(&sorted, 0), 0);
+        assert_eq!(budget_cutoff(&sorted, 1), 1);
+        assert_eq!(budget_cutoff(&sorted, 500), 1);
+        assert_eq!(budget_cutoff(&sorted, 501), 2);
+        assert_eq!(budget_cutoff(&sorted, 1000), 3);
+        // A budget larger than everything takes every folder and stays unmet
+        assert_eq!(budget_cutoff(&sorted, 5000), 3);
+        assert_eq!(budget_cutoff(&[], 100), 0);
+    }
+}
@@ -1,4 +1,10 @@
+use bytesize::ByteSize;
 use clap::{Parser, Subcommand};
+
+use rs_clean::clean::{Category, OutputFormat, RuleProtect};
+use rs_clean::combine::{CombineFormat, SortOrder, TemplateKind, TokenizerKind};
+use rs_clean::tui::ThemeName;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 
 /// rs-clean: A disk cleanup tool for developers.
@@ -7,10 +13,20 @@ use std::path::PathBuf;
 #[command(version = "0.1.0")]
 #[command(about = "Scans and cleans up project dependency folders", long_about = None)]
 pub struct Cli {
+    /// Disable colored output (also honors the NO_COLOR environment variable)
+    #[arg(long = "no-color", global = true)]
+    pub no_color: bool,
+
+    /// Replace emoji with plain ASCII markers like [!] and [ok]
+    #[arg(long = "ascii", global = true)]
+    pub ascii: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+// Parsed once at startup, so the size of the `Clean` variant does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
     /// Show version information
@@ -27,20 +43,254 @@ pub enum Commands {
     },
     /// Scan and clean up dependency folders (node_modules, target, vendor, etc.)
     Clean {
-        /// Root path to start scanning from
-        #[arg(short = 'p', long = "path", default_value = ".")]
-        path: PathBuf,
+        /// Root paths to start scanning from (repeatable, e.g. -p ~/code -p /mnt/work)
+        #[arg(
+            short = 'p',
+            long = "path",
+            env = "RS_CLEAN_PATH",
+            default_value = ".",
+            num_args = 1..
+        )]
+        paths: Vec<PathBuf>,
+
+        /// Only report what would be removed (the default)
+        #[arg(long = "dry-run", conflicts_with_all = ["delete", "force"])]
+        dry_run: bool,
+
+        /// Actually delete the matched folders
+        #[arg(long = "delete", group = "deleting")]
+        delete: bool,
 
-        /// Actually delete the folders (default is dry-run)
-        #[arg(short = 'f', long = "force")]
+        /// Deprecated alias for --delete
+        #[arg(short = 'f', long = "force", group = "deleting", hide = true)]
         force: bool,
+
+        /// Only clean folders not modified within the last DAYS days (with --newer-than, the window's near edge)
+        #[arg(long = "older-than", value_name = "DAYS")]
+        older_than: Option<u64>,
+
+        /// Only clean folders modified within the last DAYS days, e.g. a bad build from today (with --older-than, the window's far edge)
+        #[arg(long = "newer-than", value_name = "DAYS")]
+        newer_than: Option<u64>,
+
+        /// Ignore matched folders smaller than SIZE (e.g. 10MB, 1GB)
+        #[arg(long = "min-size", value_name = "SIZE")]
+        min_size: Option<ByteSize>,
+
+        /// Move folders to the system trash instead of deleting them (requires --delete)
+        #[arg(long = "trash", requires = "deleting")]
+        trash: bool,
+
+        /// Delete the largest folders first until at least SIZE has been freed (e.g. 5GB)
+        #[arg(long = "free-up", value_name = "SIZE", conflicts_with = "interactive")]
+        free_up: Option<ByteSize>,
+
+        /// Exit with code 2 if a dry run finds more than SIZE to reclaim (e.g. 1GB)
+        #[arg(
+            long = "fail-if-over",
+            value_name = "SIZE",
+            conflicts_with = "deleting"
+        )]
+        fail_if_over: Option<ByteSize>,
+
+        /// Only clean folders in these categories (comma-separated)
+        #[arg(
+            long = "category",
+            value_enum,
+            value_delimiter = ',',
+            value_name = "NAME"
+        )]
+        category: Vec<Category>,
+
+        /// Also look for project indicators up to N directories above a match (for monorepos)
+        #[arg(long = "indicator-search-depth", value_name = "N", default_value_t = 0)]
+        indicator_search_depth: usize,
+
+        /// Match folders by name alone, without checking for a project indicator (e.g. orphaned target dirs)
+        #[arg(long = "ignore-indicator", conflicts_with = "indicator_search_depth")]
+        ignore_indicator: bool,
+
+        /// Scan with one thread in sorted order for reproducible output (implies --threads 1)
+        #[arg(long = "sequential", conflicts_with = "threads")]
+        sequential: bool,
+
+        /// Keep running and report whenever the reclaimable space changes (never deletes)
+        #[arg(long = "watch", conflicts_with_all = ["deleting", "format"])]
+        watch: bool,
+
+        /// Keep running, cleaning again every --interval until stopped (requires --delete; pair with --older-than to only remove stale folders)
+        #[arg(
+            long = "daemon",
+            requires = "deleting",
+            conflicts_with_all = ["watch", "interactive"]
+        )]
+        daemon: bool,
+
+        /// Time between --daemon runs, e.g. 30m, 6h or 1d (plain numbers are seconds)
+        #[arg(
+            long = "interval",
+            value_name = "DURATION",
+            default_value = "24h",
+            value_parser = parse_interval,
+            requires = "daemon"
+        )]
+        interval: u64,
+
+        /// Delete folders even if they look in use by a running build or install (requires --delete)
+        #[arg(long = "force-busy", requires = "deleting")]
+        force_busy: bool,
+
+        /// After deleting, also remove parent folders left empty, stopping at the scan root (requires --delete)
+        #[arg(long = "prune-empty-parents", requires = "deleting")]
+        prune_empty_parents: bool,
+
+        /// Leave folders alone when their git repository has uncommitted changes (needs git on PATH)
+        #[arg(long = "skip-dirty")]
+        skip_dirty: bool,
+
+        /// Refuse to delete if more than N folders match (--delete only; dry runs are unlimited)
+        #[arg(long = "max-matches", value_name = "N", default_value_t = 50)]
+        max_matches: usize,
+
+        /// Delete however many folders match, ignoring --max-matches (requires --delete)
+        #[arg(short = 'y', long = "yes", requires = "deleting")]
+        yes: bool,
+
+        /// Allow deleting when a path is the filesystem root, your home directory or a very large folder
+        #[arg(long = "i-know-what-im-doing", requires = "deleting")]
+        i_know_what_im_doing: bool,
+
+        /// Prompt before deleting each folder (requires --delete)
+        #[arg(short = 'i', long = "interactive", requires = "deleting")]
+        interactive: bool,
+
+        /// Path to a rules config file (default: rs-clean.toml in the scan root)
+        #[arg(long = "config", value_name = "PATH")]
+        config: Option<PathBuf>,
+
+        /// Ignore the built-in rules and only use rules from the config file
+        #[arg(long = "no-default-rules")]
+        no_default_rules: bool,
+
+        /// Only use these rules, by folder name (e.g. target,node_modules)
+        #[arg(
+            long = "only",
+            value_delimiter = ',',
+            value_name = "NAMES",
+            conflicts_with = "skip"
+        )]
+        only: Vec<String>,
+
+        /// Leave out these rules, by folder name
+        #[arg(long = "skip", value_delimiter = ',', value_name = "NAMES")]
+        skip: Vec<String>,
+
+        /// Output format
+        #[arg(long = "format", value_enum, default_value_t = OutputFormat::Human)]
+        format: OutputFormat,
+
+        /// Run CMD through the shell when done, with RS_CLEAN_FREED_BYTES, RS_CLEAN_COUNT, RS_CLEAN_DELETED, RS_CLEAN_FAILED and RS_CLEAN_DRY_RUN set
+        #[arg(long = "on-complete", value_name = "CMD")]
+        on_complete: Option<String>,
+
+        /// Also write the JSON report to PATH, keeping the normal output on stdout
+        #[arg(long = "report", value_name = "PATH")]
+        report: Option<PathBuf>,
+
+        /// Append a JSON line per deleted folder (time, path, size, outcome) to PATH, as an audit trail (requires --delete)
+        #[arg(long = "log", value_name = "PATH", requires = "deleting")]
+        log: Option<PathBuf>,
+
+        /// Only print the final summary (for cron jobs)
+        #[arg(short = 'q', long = "quiet", conflicts_with = "verbose")]
+        quiet: bool,
+
+        /// Like --quiet, but print nothing at all unless something was found, freed or failed (for scheduled runs)
+        #[arg(long = "summary-only-on-change", conflicts_with = "verbose")]
+        summary_only_on_change: bool,
+
+        /// Also print skipped folders and why they were skipped
+        #[arg(short = 'v', long = "verbose")]
+        verbose: bool,
+
+        /// .gitignore-style pattern for folders to never clean (repeatable, relative to the scan root; later patterns win, `!` negates)
+        #[arg(short = 'x', long = "exclude", value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        /// Never clean RULE's folders matching GLOB, e.g. target:~/important/** (repeatable; GLOB is .gitignore-style like --exclude)
+        #[arg(long = "protect", value_name = "RULE:GLOB", value_parser = parse_protect)]
+        protect: Vec<RuleProtect>,
+
+        /// Skip anything excluded by .gitignore, .ignore or the global git excludes
+        #[arg(long = "respect-gitignore", overrides_with = "no_respect_gitignore")]
+        respect_gitignore: bool,
+
+        /// Don't skip ignored folders, even when the config file turns respect_gitignore on
+        #[arg(long = "no-respect-gitignore")]
+        no_respect_gitignore: bool,
+
+        /// Also descend into hidden (dot-prefixed) directories; dot-named rule folders like .venv are always found
+        #[arg(long = "hidden", overrides_with = "no_hidden")]
+        hidden: bool,
+
+        /// Don't descend into hidden directories, even when the config file turns hidden on
+        #[arg(long = "no-hidden")]
+        no_hidden: bool,
+
+        /// Also read ignore files from parent directories of the scan root (with --respect-gitignore)
+        #[arg(long = "parents", requires = "respect_gitignore")]
+        parents: bool,
+
+        /// Also match folders that are symlinks; only the link is removed, never its target
+        #[arg(long = "follow-symlinks")]
+        follow_symlinks: bool,
+
+        /// Number of threads to scan with (default: number of CPUs; 1 = sequential)
+        #[arg(short = 'j', long = "threads", value_name = "N")]
+        threads: Option<NonZeroUsize>,
+
+        /// Maximum directory depth to scan (0 = only the root, 1 = its children, ...)
+        #[arg(short = 'd', long = "max-depth", value_name = "N")]
+        max_depth: Option<usize>,
+
+        /// Stop sizing a folder after SECS seconds and report its size as unknown (for slow or hung mounts)
+        #[arg(long = "size-timeout", value_name = "SECS")]
+        size_timeout: Option<u64>,
+
+        /// Cache folder sizes between runs; a folder is re-measured when its mtime changes (dry runs without age filters only)
+        #[arg(long = "cache")]
+        cache: bool,
+
+        /// Measure allocated disk space instead of apparent file size (Unix only; default: apparent size)
+        #[arg(long = "on-disk")]
+        on_disk: bool,
+
+        /// Number of largest matches listed individually in the dry-run report
+        #[arg(long = "top", value_name = "N", default_value_t = 10)]
+        top: usize,
+
+        /// Never show the progress spinner, even on a terminal
+        #[arg(long = "no-progress")]
+        no_progress: bool,
+
+        /// Leave the throughput and ETA out of the deletion progress bar
+        #[arg(long = "no-eta")]
+        no_eta: bool,
     },
     /// Combine code files into a single Markdown file
     CombineCode {
+        /// Files, or globs relative to --path, to combine instead of everything (e.g. 'src/**/*.rs' Cargo.toml)
+        #[arg(value_name = "PATHS")]
+        paths: Vec<String>,
+
         /// Root path to scan
-        #[arg(short = 'p', long = "path", default_value = ".")]
+        #[arg(short = 'p', long = "path", env = "RS_CLEAN_PATH", default_value = ".")]
         path: PathBuf,
 
+        /// Combine exactly the files listed on stdin, one per line (e.g. from `git ls-files`)
+        #[arg(long = "stdin", conflicts_with_all = ["paths", "include_path", "exclude_path"])]
+        stdin: bool,
+
         /// Output file path (default: stdout)
         #[arg(short = 'o', long = "output")]
         output: Option<PathBuf>,
@@ -52,7 +302,200 @@ pub enum Commands {
         /// Comma-separated list of file extensions to exclude
         #[arg(short = 'e', long = "exclude", value_delimiter = ',')]
         exclude: Vec<String>,
+
+        /// Only include files whose path relative to --path matches GLOB (repeatable, e.g. 'src/**')
+        #[arg(long = "include-path", value_name = "GLOB")]
+        include_path: Vec<String>,
+
+        /// Skip files or directories whose relative path matches GLOB (repeatable, e.g. tests/ or '**/generated.rs')
+        #[arg(long = "exclude-path", value_name = "GLOB")]
+        exclude_path: Vec<String>,
+
+        /// Keep lock files like Cargo.lock and package-lock.json, e.g. for dependency analysis
+        #[arg(long = "include-lockfiles")]
+        include_lockfiles: bool,
+
+        /// Don't honor .gitignore and .ignore files
+        #[arg(long = "no-ignore")]
+        no_ignore: bool,
+
+        /// Skip files larger than this (e.g. 500KB, 2MB)
+        #[arg(long = "max-file-size", value_name = "SIZE")]
+        max_file_size: Option<ByteSize>,
+
+        /// Stop adding files once the estimated token count would exceed N
+        #[arg(long = "max-tokens", value_name = "N")]
+        max_tokens: Option<usize>,
+
+        /// How tokens are counted for the stats and --max-tokens
+        #[arg(long = "tokenizer", value_enum, default_value_t = TokenizerKind::Heuristic)]
+        tokenizer: TokenizerKind,
+
+        /// Output format
+        #[arg(long = "format", value_enum, default_value_t = CombineFormat::Markdown)]
+        format: CombineFormat,
+
+        /// How each file is wrapped in the Markdown output: markdown, xml or plain
+        #[arg(long = "template", value_enum, default_value_t = TemplateKind::Markdown)]
+        template: TemplateKind,
+
+        /// Order files by name, size or ext (default: name; with --max-tokens, shallow and small files first)
+        #[arg(long = "sort", value_enum)]
+        sort: Option<SortOrder>,
+
+        /// Don't print the directory tree before the file contents
+        #[arg(long = "no-tree")]
+        no_tree: bool,
+
+        /// Prefix each line in the code blocks with its line number
+        #[arg(long = "line-numbers")]
+        line_numbers: bool,
+
+        /// Copy the combined output to the clipboard instead of printing it
+        #[arg(long = "clipboard", conflicts_with = "output")]
+        clipboard: bool,
+
+        /// Only print the file count, size and token estimate
+        #[arg(long = "stats-only", conflicts_with_all = ["output", "clipboard"])]
+        stats_only: bool,
+
+        /// Gzip the output, appending .gz to the --output file name (stdout only when redirected)
+        #[arg(long = "gzip", conflicts_with_all = ["clipboard", "stats_only"])]
+        gzip: bool,
+
+        /// List the files that would be combined, with size and token estimate, without writing content
+        #[arg(long = "list", conflicts_with_all = ["output", "clipboard", "stats_only", "format"])]
+        list: bool,
+
+        /// Like --list, but also show the first N lines of each file
+        #[arg(
+            long = "preview",
+            value_name = "N",
+            conflicts_with_all = ["output", "clipboard", "stats_only", "format"]
+        )]
+        preview: Option<usize>,
     },
+    /// Restore the folders moved to the trash by the most recent `clean --trash`
+    Restore,
     /// Interactive Terminal UI
-    Tui,
+    Tui {
+        /// Color scheme (default: dark, or `tui.theme` from the config file)
+        #[arg(long = "theme", value_enum)]
+        theme: Option<ThemeName>,
+    },
+}
+
+/// Split a `--protect` value at its first `:`, expanding a leading `~/` in the glob.
+fn parse_protect(value: &str) -> Result<RuleProtect, String> {
+    let (rule, pattern) = value
+        .split_once(':')
+        .filter(|(rule, pattern)| !rule.is_empty() && !pattern.is_empty())
+        .ok_or_else(|| {
+            format!(
+                "expected RULE:GLOB, e.g. target:~/important/**, got {:?}",
+                value
+            )
+        })?;
+    let pattern = match (pattern.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        _ => pattern.to_string(),
+    };
+    Ok(RuleProtect {
+        rule: rule.to_string(),
+        pattern,
+    })
+}
+
+/// Parse a `--interval` like `90s`, `30m`, `6h` or `1d` into seconds.
+fn parse_interval(value: &str) -> Result<u64, String> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("expected a duration like 30m, 6h or 1d, got {:?}", value))?;
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("unknown unit {:?}; use s, m, h or d", unit)),
+    };
+    match number.checked_mul(seconds) {
+        Some(0) => Err("the interval must be greater than zero".to_string()),
+        Some(secs) => Ok(secs),
+        None => Err(format!("interval {:?} is too long", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tui_subcommand() {
+        let cli = Cli::try_parse_from(["rs-clean", "tui"]).unwrap();
+        assert!(matches!(cli.command, Commands::Tui { theme: None }));
+    }
+
+    /// The `hidden` and `no_hidden` flags of a parsed `clean` command line.
+    fn hidden_flags(args: &[&str]) -> (bool, bool) {
+        let cli = Cli::try_parse_from(["rs-clean", "clean"].iter().chain(args)).unwrap();
+        match cli.command {
+            Commands::Clean {
+                hidden, no_hidden, ..
+            } => (hidden, no_hidden),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn last_of_hidden_and_no_hidden_wins() {
+        assert_eq!(hidden_flags(&["--no-hidden"]), (false, true));
+        assert_eq!(hidden_flags(&["--hidden", "--no-hidden"]), (false, true));
+        assert_eq!(hidden_flags(&["--no-hidden", "--hidden"]), (true, false));
+    }
+
+    #[test]
+    fn parses_intervals_with_and_without_units() {
+        assert_eq!(parse_interval("90"), Ok(90));
+        assert_eq!(parse_interval("90s"), Ok(90));
+        assert_eq!(parse_interval("30m"), Ok(30 * 60));
+        assert_eq!(parse_interval("6h"), Ok(6 * 60 * 60));
+        assert_eq!(parse_interval("1d"), Ok(24 * 60 * 60));
+    }
+
+    #[test]
+    fn rejects_zero_unknown_and_overflowing_intervals() {
+        for value in ["0", "0h", "", "h", "1w", "1.5h", "-1", "1 h"] {
+            assert!(parse_interval(value).is_err(), "{:?} was accepted", value);
+        }
+        assert_eq!(
+            parse_interval("0d"),
+            Err("the interval must be greater than zero".to_string())
+        );
+        assert_eq!(
+            parse_interval(&format!("{}d", u64::MAX / 60)),
+            Err(format!("interval \"{}d\" is too long", u64::MAX / 60))
+        );
+        // Too many digits for a u64 at all
+        assert!(parse_interval("99999999999999999999s").is_err());
+    }
+
+    #[test]
+    fn parses_protect_pairs() {
+        let protect = parse_protect("target:vendor/**").unwrap();
+        assert_eq!(protect.rule, "target");
+        assert_eq!(protect.pattern, "vendor/**");
+
+        if let Some(home) = dirs::home_dir() {
+            let protect = parse_protect("target:~/important/**").unwrap();
+            assert_eq!(protect.pattern, home.join("important/**").to_string_lossy());
+        }
+
+        for value in ["target", ":vendor/**", "target:"] {
+            assert!(parse_protect(value).is_err(), "{:?} was accepted", value);
+        }
+    }
 }
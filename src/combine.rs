@@ -1,40 +1,921 @@
+use crate::term::{self, say};
 use bytesize::ByteSize;
+use crossterm::style::Color;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::path::Path;
-use walkdir::WalkDir;
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Bytes read from the start of each file to decide whether it is binary
+const SNIFF_LEN: usize = 8192;
+
+/// Target size of the line-aligned chunks files are streamed in
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// Files counted in parallel per thread before the batch is streamed out in order
+const COUNT_BATCH_PER_THREAD: usize = 8;
+
+/// Hidden first argument that turns the process into the clipboard holder on Linux
+pub const CLIPBOARD_ARG: &str = "__serve-clipboard";
+
+/// Project-level .gitignore-style file listing what combine-code always leaves out
+const IGNORE_FILE_NAME: &str = ".rs-cleanignore";
+
+/// Dependency lock files: generated noise in most dumps, kept with `--include-lockfiles`
+const LOCKFILES: &[&str] = &[
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "Cargo.lock",
+    "composer.lock",
+];
+
+/// Other file names skipped unless `combine.ignored_files` in the config replaces the list
+const JUNK_FILES: &[&str] = &[".DS_Store", "Thumbs.db", ".env"];
+
+/// Which tokenizer `combine-code` uses to estimate token counts.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum TokenizerKind {
+    /// 4 characters per token
+    #[default]
+    Heuristic,
+    /// One token per whitespace-separated word
+    Whitespace,
+    /// Exact GPT counts (o200k_base); requires the `tiktoken` feature
+    Tiktoken,
+}
+
+/// Counts the tokens a piece of text would cost a model.
+pub trait Tokenizer: Sync {
+    /// Label shown next to the token count in the stats
+    fn name(&self) -> &'static str;
+    fn count(&self, text: &str) -> usize;
+}
 
 /// Simple heuristic: 4 chars ~= 1 token
-fn estimate_tokens(text: &str) -> usize {
-    text.chars().count() / 4
+struct Heuristic;
+
+impl Tokenizer for Heuristic {
+    fn name(&self) -> &'static str {
+        "Heuristic: chars/4"
+    }
+
+    fn count(&self, text: &str) -> usize {
+        text.chars().count() / 4
+    }
+}
+
+struct Whitespace;
+
+impl Tokenizer for Whitespace {
+    fn name(&self) -> &'static str {
+        "Whitespace: words"
+    }
+
+    fn count(&self, text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+}
+
+#[cfg(feature = "tiktoken")]
+struct Tiktoken(&'static tiktoken_rs::CoreBPE);
+
+#[cfg(feature = "tiktoken")]
+impl Tokenizer for Tiktoken {
+    fn name(&self) -> &'static str {
+        "tiktoken: o200k_base"
+    }
+
+    fn count(&self, text: &str) -> usize {
+        self.0.encode_with_special_tokens(text).len()
+    }
+}
+
+fn make_tokenizer(kind: TokenizerKind) -> Result<Box<dyn Tokenizer>, String> {
+    match kind {
+        TokenizerKind::Heuristic => Ok(Box::new(Heuristic)),
+        TokenizerKind::Whitespace => Ok(Box::new(Whitespace)),
+        #[cfg(feature = "tiktoken")]
+        TokenizerKind::Tiktoken => Ok(Box::new(Tiktoken(tiktoken_rs::o200k_base_singleton()))),
+        #[cfg(not(feature = "tiktoken"))]
+        TokenizerKind::Tiktoken => Err(
+            "The tiktoken tokenizer is not available; rebuild with `--features tiktoken`."
+                .to_string(),
+        ),
+    }
+}
+
+/// Order files are written in by `combine-code --sort`.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum SortOrder {
+    /// By relative path
+    Name,
+    /// Smallest first, then by path
+    Size,
+    /// Grouped by extension, then by path
+    Ext,
+}
+
+/// Output format for `combine-code`.
+#[derive(Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum CombineFormat {
+    /// Fenced Markdown code blocks
+    #[default]
+    Markdown,
+    /// A JSON object with one entry per file and a summary
+    Json,
+}
+
+/// Wrapper style around each file in the Markdown (text) output.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum TemplateKind {
+    /// `# File:` headings and fenced code blocks
+    #[default]
+    Markdown,
+    /// `<file path="...">` elements, as preferred by some models
+    Xml,
+    /// `==> path <==` separators and no fences
+    Plain,
+}
+
+/// The text written around the tree and each file's content.
+trait Template {
+    fn tree(&self, tree: &str) -> String;
+    fn file_header(&self, path: &str, language: &str) -> String;
+    /// Written after the content, which always ends in a newline.
+    fn file_footer(&self) -> String;
+    /// Stands in for a file left out of the output, with the reason why.
+    fn omitted(&self, path: &str, reason: &str) -> String;
+}
+
+struct MarkdownTemplate;
+
+impl Template for MarkdownTemplate {
+    fn tree(&self, tree: &str) -> String {
+        format!("# Directory Tree\n```\n{}```\n", tree)
+    }
+
+    fn file_header(&self, path: &str, language: &str) -> String {
+        format!("\n# File: {}\n```{}\n", path, language)
+    }
+
+    fn file_footer(&self) -> String {
+        "```\n".to_string()
+    }
+
+    fn omitted(&self, path: &str, reason: &str) -> String {
+        format!("\n# File: {}\n<!-- omitted: {} -->\n", path, reason)
+    }
+}
+
+struct XmlTemplate;
+
+/// Escape a value for use inside a double-quoted XML attribute.
+fn xml_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
 }
 
-pub fn combine_code(
-    root: &Path,
-    output_path: Option<&Path>,
-    include: &[String],
-    exclude: &[String],
-) {
-    use std::io::Write;
+impl Template for XmlTemplate {
+    fn tree(&self, tree: &str) -> String {
+        format!("<directory_tree>\n{}</directory_tree>\n", tree)
+    }
+
+    fn file_header(&self, path: &str, language: &str) -> String {
+        format!(
+            "\n<file path=\"{}\" language=\"{}\">\n",
+            xml_attr(path),
+            xml_attr(language)
+        )
+    }
+
+    fn file_footer(&self) -> String {
+        "</file>\n".to_string()
+    }
+
+    fn omitted(&self, path: &str, reason: &str) -> String {
+        format!(
+            "\n<file path=\"{}\" omitted=\"{}\"/>\n",
+            xml_attr(path),
+            xml_attr(reason)
+        )
+    }
+}
+
+struct PlainTemplate;
+
+impl Template for PlainTemplate {
+    fn tree(&self, tree: &str) -> String {
+        format!("Directory tree:\n{}", tree)
+    }
+
+    fn file_header(&self, path: &str, _language: &str) -> String {
+        format!("\n==> {} <==\n", path)
+    }
+
+    fn file_footer(&self) -> String {
+        String::new()
+    }
+
+    fn omitted(&self, path: &str, reason: &str) -> String {
+        format!("\n==> {} <== (omitted: {})\n", path, reason)
+    }
+}
+
+fn make_template(kind: TemplateKind) -> Box<dyn Template> {
+    match kind {
+        TemplateKind::Markdown => Box::new(MarkdownTemplate),
+        TemplateKind::Xml => Box::new(XmlTemplate),
+        TemplateKind::Plain => Box::new(PlainTemplate),
+    }
+}
+
+/// A single file included in the combined output.
+#[derive(Serialize)]
+struct CombinedFile {
+    path: String,
+    language: String,
+    size: u64,
+    tokens: usize,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct SkippedFile {
+    path: String,
+    size: u64,
+}
+
+#[derive(Serialize)]
+struct CombineSummary {
+    files: usize,
+    total_bytes: u64,
+    lines: usize,
+    tokens: usize,
+    tokenizer: &'static str,
+    skipped_large: Vec<SkippedFile>,
+    skipped_binary: Vec<String>,
+    dropped: Vec<String>,
+}
+
+/// Renders files into the combined output.
+trait Formatter {
+    fn write_tree(&mut self, out: &mut dyn Write, tree: &str) -> io::Result<()>;
+    /// Start a file; `lines` is its line count.
+    fn begin_file(
+        &mut self,
+        out: &mut dyn Write,
+        path: &str,
+        language: &str,
+        lines: usize,
+    ) -> io::Result<()>;
+    /// Write a run of whole lines from the current file.
+    fn write_chunk(&mut self, out: &mut dyn Write, chunk: &str) -> io::Result<()>;
+    fn end_file(&mut self, out: &mut dyn Write, size: u64, tokens: usize) -> io::Result<()>;
+    fn write_omitted(
+        &mut self,
+        out: &mut dyn Write,
+        file: &SkippedFile,
+        limit: ByteSize,
+    ) -> io::Result<()>;
+    fn finish(&mut self, out: &mut dyn Write, summary: &CombineSummary) -> io::Result<()>;
+}
+
+/// Each file's content wrapped by a `Template`, fenced Markdown by default.
+struct Markdown {
+    template: Box<dyn Template>,
+    /// Prefix each line with its right-aligned line number
+    line_numbers: bool,
+    /// Width of the line number column for the current file
+    width: usize,
+    next_line: usize,
+}
+
+impl Formatter for Markdown {
+    fn write_tree(&mut self, out: &mut dyn Write, tree: &str) -> io::Result<()> {
+        out.write_all(self.template.tree(tree).as_bytes())
+    }
+
+    fn begin_file(
+        &mut self,
+        out: &mut dyn Write,
+        path: &str,
+        language: &str,
+        lines: usize,
+    ) -> io::Result<()> {
+        self.width = lines.max(1).to_string().len();
+        self.next_line = 1;
+        out.write_all(self.template.file_header(path, language).as_bytes())
+    }
+
+    fn write_chunk(&mut self, out: &mut dyn Write, chunk: &str) -> io::Result<()> {
+        if !self.line_numbers {
+            return out.write_all(chunk.as_bytes());
+        }
+        for line in chunk.lines() {
+            writeln!(
+                out,
+                "{:>width$} | {}",
+                self.next_line,
+                line,
+                width = self.width
+            )?;
+            self.next_line += 1;
+        }
+        Ok(())
+    }
+
+    fn end_file(&mut self, out: &mut dyn Write, _size: u64, _tokens: usize) -> io::Result<()> {
+        // Numbered lines always end in a newline; raw content may not
+        if !self.line_numbers {
+            writeln!(out)?;
+        }
+        out.write_all(self.template.file_footer().as_bytes())
+    }
+
+    fn write_omitted(
+        &mut self,
+        out: &mut dyn Write,
+        file: &SkippedFile,
+        limit: ByteSize,
+    ) -> io::Result<()> {
+        let reason = format!("{} exceeds --max-file-size {}", ByteSize(file.size), limit);
+        out.write_all(self.template.omitted(&file.path, &reason).as_bytes())
+    }
+
+    fn finish(&mut self, _out: &mut dyn Write, _summary: &CombineSummary) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Collects every file and writes a single JSON document at the end.
+///
+/// Unlike Markdown this has to hold file contents until the document is written.
+#[derive(Default)]
+struct Json {
+    tree: Option<String>,
+    files: Vec<CombinedFile>,
+    current: Option<CombinedFile>,
+}
+
+#[derive(Serialize)]
+struct JsonDocument<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tree: Option<&'a str>,
+    files: &'a [CombinedFile],
+    summary: &'a CombineSummary,
+}
+
+impl Formatter for Json {
+    fn write_tree(&mut self, _out: &mut dyn Write, tree: &str) -> io::Result<()> {
+        self.tree = Some(tree.to_string());
+        Ok(())
+    }
+
+    fn begin_file(
+        &mut self,
+        _out: &mut dyn Write,
+        path: &str,
+        language: &str,
+        _lines: usize,
+    ) -> io::Result<()> {
+        self.current = Some(CombinedFile {
+            path: path.to_string(),
+            language: language.to_string(),
+            size: 0,
+            tokens: 0,
+            content: String::new(),
+        });
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, _out: &mut dyn Write, chunk: &str) -> io::Result<()> {
+        if let Some(file) = &mut self.current {
+            file.content.push_str(chunk);
+        }
+        Ok(())
+    }
+
+    fn end_file(&mut self, _out: &mut dyn Write, size: u64, tokens: usize) -> io::Result<()> {
+        if let Some(mut file) = self.current.take() {
+            file.size = size;
+            file.tokens = tokens;
+            self.files.push(file);
+        }
+        Ok(())
+    }
+
+    fn write_omitted(
+        &mut self,
+        _out: &mut dyn Write,
+        _file: &SkippedFile,
+        _limit: ByteSize,
+    ) -> io::Result<()> {
+        // Omitted files are listed in the summary
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut dyn Write, summary: &CombineSummary) -> io::Result<()> {
+        let document = JsonDocument {
+            tree: self.tree.as_deref(),
+            files: &self.files,
+            summary,
+        };
+        serde_json::to_writer_pretty(&mut *out, &document)?;
+        writeln!(out)
+    }
+}
+
+/// One line per file that would be combined, for `--list`; no content is written.
+#[derive(Default)]
+struct List {
+    current: String,
+    /// Lines of each file to show under it (`--preview`)
+    preview: usize,
+    head: Vec<String>,
+}
+
+impl Formatter for List {
+    fn write_tree(&mut self, out: &mut dyn Write, _tree: &str) -> io::Result<()> {
+        writeln!(out, "{}", term::text("📋 Files that would be combined:"))
+    }
+
+    fn begin_file(
+        &mut self,
+        _out: &mut dyn Write,
+        path: &str,
+        _language: &str,
+        _lines: usize,
+    ) -> io::Result<()> {
+        self.current = path.to_string();
+        self.head.clear();
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, _out: &mut dyn Write, chunk: &str) -> io::Result<()> {
+        let wanted = self.preview - self.head.len();
+        self.head
+            .extend(chunk.lines().take(wanted).map(str::to_string));
+        Ok(())
+    }
+
+    fn end_file(&mut self, out: &mut dyn Write, size: u64, tokens: usize) -> io::Result<()> {
+        writeln!(
+            out,
+            "   {} ({}, ~{} tokens)",
+            self.current,
+            ByteSize(size),
+            tokens
+        )?;
+        for line in &self.head {
+            writeln!(out, "      | {}", line)?;
+        }
+        Ok(())
+    }
+
+    fn write_omitted(
+        &mut self,
+        out: &mut dyn Write,
+        file: &SkippedFile,
+        limit: ByteSize,
+    ) -> io::Result<()> {
+        writeln!(
+            out,
+            "   {} ({}, omitted: over {})",
+            file.path,
+            ByteSize(file.size),
+            limit
+        )
+    }
+
+    fn finish(&mut self, out: &mut dyn Write, summary: &CombineSummary) -> io::Result<()> {
+        for path in &summary.dropped {
+            writeln!(out, "   {} (dropped: over --max-tokens)", path)?;
+        }
+        writeln!(
+            out,
+            "{}",
+            term::text(&format!(
+                "📊 Total: {} file(s), {} line(s), {}, ~{} tokens ({})",
+                summary.files,
+                summary.lines,
+                ByteSize(summary.total_bytes),
+                summary.tokens,
+                summary.tokenizer
+            ))
+        )
+    }
+}
+
+#[derive(Default)]
+struct TreeNode(BTreeMap<String, TreeNode>);
+
+/// Render relative paths as a `tree`-style listing.
+fn render_tree<'a>(paths: impl Iterator<Item = &'a Path>) -> String {
+    let mut root = TreeNode::default();
+    for path in paths {
+        let mut node = &mut root;
+        for component in path.components() {
+            let name = component.as_os_str().to_string_lossy().into_owned();
+            node = node.0.entry(name).or_default();
+        }
+    }
+
+    fn walk(node: &TreeNode, prefix: &str, out: &mut String) {
+        let count = node.0.len();
+        for (i, (name, child)) in node.0.iter().enumerate() {
+            let last = i + 1 == count;
+            out.push_str(prefix);
+            out.push_str(if last { "└── " } else { "├── " });
+            out.push_str(name);
+            out.push('\n');
+            let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            walk(child, &child_prefix, out);
+        }
+    }
+
+    let mut out = String::from(".\n");
+    walk(&root, "", &mut out);
+    term::text(&out).into_owned()
+}
+
+/// Language tag for a file's fenced code block, so viewers highlight it correctly.
+fn language_for(path: &Path) -> String {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let by_name = match file_name {
+        "Dockerfile" | "Containerfile" => Some("dockerfile"),
+        "Makefile" | "GNUmakefile" | "makefile" => Some("makefile"),
+        "CMakeLists.txt" => Some("cmake"),
+        "Jenkinsfile" => Some("groovy"),
+        "Gemfile" | "Rakefile" => Some("ruby"),
+        "Justfile" | "justfile" => Some("just"),
+        _ if file_name.starts_with("Dockerfile.") => Some("dockerfile"),
+        _ => None,
+    };
+    if let Some(language) = by_name {
+        return language.to_string();
+    }
+
+    let Some(ext) = path.extension().and_then(|s| s.to_str()) else {
+        return String::new();
+    };
+    let ext = ext.to_lowercase();
+    let language = match ext.as_str() {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "rb" => "ruby",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "cs" => "csharp",
+        "fs" | "fsx" => "fsharp",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => "cpp",
+        "m" => "objectivec",
+        "swift" => "swift",
+        "php" => "php",
+        "sh" | "bash" => "bash",
+        "zsh" => "zsh",
+        "ps1" => "powershell",
+        "yml" | "yaml" => "yaml",
+        "md" | "markdown" => "markdown",
+        "htm" | "html" => "html",
+        "scss" => "scss",
+        "ex" | "exs" => "elixir",
+        "erl" | "hrl" => "erlang",
+        "hs" => "haskell",
+        "ml" | "mli" => "ocaml",
+        "clj" | "cljs" => "clojure",
+        "tf" => "hcl",
+        "proto" => "protobuf",
+        "vue" => "vue",
+        "dart" => "dart",
+        "lua" => "lua",
+        "sql" => "sql",
+        other => other,
+    };
+    language.to_string()
+}
+
+/// Sniff the start of a file: a NUL byte or invalid UTF-8 means it isn't source text.
+fn looks_binary(path: &Path) -> bool {
+    let mut head = [0u8; SNIFF_LEN];
+    let read = match fs::File::open(path).and_then(|mut f| f.read(&mut head)) {
+        Ok(n) => n,
+        Err(_) => return true,
+    };
+    let head = &head[..read];
+    if head.contains(&0) {
+        return true;
+    }
+    // A multi-byte character cut off at the end of the sample is still valid text
+    match std::str::from_utf8(head) {
+        Ok(_) => false,
+        Err(e) => e.error_len().is_some(),
+    }
+}
+
+/// Stream a file as UTF-8 chunks of whole lines, replacing any invalid bytes.
+fn read_chunks(path: &Path, mut on_chunk: impl FnMut(&str) -> io::Result<()>) -> io::Result<()> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let mut chunk = Vec::with_capacity(CHUNK_LEN);
+    loop {
+        let read = reader.read_until(b'\n', &mut chunk)?;
+        if (read == 0 || chunk.len() >= CHUNK_LEN) && !chunk.is_empty() {
+            on_chunk(&String::from_utf8_lossy(&chunk))?;
+            chunk.clear();
+        }
+        if read == 0 {
+            return Ok(());
+        }
+    }
+}
+
+/// What the parallel pass learns about a file before it is streamed out in order.
+struct FileCounts {
+    size: u64,
+    tokens: usize,
+    lines: usize,
+}
+
+/// Read a file through once to count its tokens and lines, without keeping the content.
+fn count_file(path: &Path, tokenizer: &dyn Tokenizer) -> io::Result<FileCounts> {
+    let mut counts = FileCounts {
+        size: 0,
+        tokens: 0,
+        lines: 0,
+    };
+    read_chunks(path, |chunk| {
+        counts.size += chunk.len() as u64;
+        counts.tokens += tokenizer.count(chunk);
+        counts.lines += chunk.lines().count();
+        Ok(())
+    })?;
+    Ok(counts)
+}
+
+/// Counts the bytes passing through to the inner writer.
+struct Counter<'a, W> {
+    inner: W,
+    count: &'a Cell<u64>,
+}
+
+impl<W: Write> Write for Counter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count.set(self.count.get() + written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// `path` with `.gz` appended, unless it already ends in it.
+fn gz_path(path: &Path) -> PathBuf {
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        return path.to_path_buf();
+    }
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".gz");
+    PathBuf::from(name)
+}
+
+/// Absolute, symlink-free form of the output path, even if the file doesn't exist yet.
+fn canonical_output(path: &Path) -> Option<PathBuf> {
+    path.canonicalize().ok().or_else(|| {
+        let parent = path.parent().filter(|p| !p.as_os_str().is_empty());
+        let parent = parent.unwrap_or(Path::new(".")).canonicalize().ok()?;
+        Some(parent.join(path.file_name()?))
+    })
+}
+
+/// Match glob patterns against paths relative to the root.
+fn build_selection(patterns: &[&String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let relative = pattern.strip_prefix("./").unwrap_or(pattern);
+        // `tests/` names the directory itself, which `matches_path` checks like any file
+        let relative = relative.trim_end_matches('/');
+        let glob = GlobBuilder::new(relative)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| format!("Invalid pattern {:?}: {}", pattern, e))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Invalid patterns: {}", e))
+}
+
+/// Whether `rel_path` or any directory above it matches one of the globs.
+fn matches_path(set: &GlobSet, rel_path: &Path) -> bool {
+    rel_path
+        .ancestors()
+        .any(|p| !p.as_os_str().is_empty() && set.is_match(p))
+}
+
+/// Options controlling which files are combined and how the output is written.
+#[derive(Default)]
+pub struct CombineOptions {
+    /// Explicit files or globs to combine instead of the filtered walk
+    pub paths: Vec<String>,
+    /// Output file path; stdout when unset
+    pub output: Option<PathBuf>,
+    /// Only include files with these extensions
+    pub include: Vec<String>,
+    /// Skip files with these extensions
+    pub exclude: Vec<String>,
+    /// Only include files whose relative path (or a parent directory) matches one of these globs
+    pub include_paths: Vec<String>,
+    /// Skip files whose relative path (or a parent directory) matches one of these globs
+    pub exclude_paths: Vec<String>,
+    /// Walk without honoring .gitignore and .ignore files
+    pub no_ignore: bool,
+    /// Leave out files larger than this, with a placeholder
+    pub max_file_size: Option<ByteSize>,
+    /// Stop adding files once the token count would exceed this
+    pub max_tokens: Option<usize>,
+    /// How tokens are counted
+    pub tokenizer: TokenizerKind,
+    /// Markdown or JSON output
+    pub format: CombineFormat,
+    /// How files are wrapped in the Markdown output
+    pub template: TemplateKind,
+    /// Leave out the directory tree header
+    pub no_tree: bool,
+    /// Number each line of the Markdown code blocks
+    pub line_numbers: bool,
+    /// Copy the result to the clipboard when no output file is set
+    pub clipboard: bool,
+    /// Only report file count, size and tokens; write no content
+    pub stats_only: bool,
+    /// Print nothing but errors, for callers that show the returned stats themselves
+    pub quiet: bool,
+    /// Read newline-separated file paths from stdin instead of walking the root
+    pub stdin: bool,
+    /// Gzip the output file (or stdout, when it is redirected)
+    pub gzip: bool,
+    /// File names to skip; `None` means the lock files and OS junk skipped by default
+    pub ignored_files: Option<Vec<String>>,
+    /// Keep lock files even when they are in the ignored file names
+    pub include_lockfiles: bool,
+    /// Explicit file order; by default files go by path, or shallow and small first with `max_tokens`
+    pub sort: Option<SortOrder>,
+    /// Print each file that would be combined with its size and tokens instead of the content
+    pub list: bool,
+    /// With `list`, also show the first this many lines of each file
+    pub preview: Option<usize>,
+}
+
+/// Totals for a finished `combine_code` run.
+pub struct CombineStats {
+    pub files: usize,
+    pub total_bytes: u64,
+    pub lines: usize,
+    pub tokens: usize,
+}
+
+/// Patterns from `root/.rs-cleanignore`, or an empty matcher when there is none.
+fn load_project_ignore(root: &Path) -> Result<Gitignore, String> {
+    let path = root.join(IGNORE_FILE_NAME);
+    if !path.is_file() {
+        return Ok(Gitignore::empty());
+    }
+    let mut builder = GitignoreBuilder::new(root);
+    if let Some(e) = builder.add(&path) {
+        return Err(format!("Invalid {:?}: {}", path, e));
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Invalid {:?}: {}", path, e))
+}
+
+/// Put `text` on the system clipboard.
+#[cfg(not(target_os = "linux"))]
+fn copy_to_clipboard(text: String) -> Result<(), String> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .map_err(|e| e.to_string())
+}
+
+/// Put `text` on the system clipboard.
+///
+/// X11 and Wayland clipboards are served by the process that set them and go empty when it
+/// exits, so a detached copy of this program holds the text until something else is copied.
+#[cfg(target_os = "linux")]
+fn copy_to_clipboard(text: String) -> Result<(), String> {
+    use std::process::{Command, Stdio};
+
+    // Fail here, while stdout is still a fallback, when there is no clipboard at all
+    arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    let mut holder = std::env::current_exe()
+        .and_then(|exe| {
+            Command::new(exe)
+                .arg(CLIPBOARD_ARG)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .current_dir("/")
+                .spawn()
+        })
+        .map_err(|e| e.to_string())?;
+    let mut stdin = holder.stdin.take().expect("stdin is piped");
+    stdin.write_all(text.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Run as the clipboard holder: copy stdin to the clipboard and serve it until it is replaced.
+#[cfg(target_os = "linux")]
+pub fn serve_clipboard() -> std::process::ExitCode {
+    use arboard::SetExtLinux;
+
+    let mut text = String::new();
+    if io::stdin().read_to_string(&mut text).is_err() {
+        return std::process::ExitCode::FAILURE;
+    }
+    match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set().wait().text(text)) {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(_) => std::process::ExitCode::FAILURE,
+    }
+}
+
+/// Combine the selected files under `root`; errors that stop the run are returned.
+pub fn combine_code(root: &Path, options: &CombineOptions) -> Result<CombineStats, String> {
+    // Compressing only makes sense for real content going to a file or a pipe
+    let gzip = options.gzip
+        && !options.stats_only
+        && !options.list
+        && !options.clipboard
+        && (options.output.is_some() || !io::stdout().is_terminal());
+    let output_file = match &options.output {
+        Some(path) if gzip => Some(gz_path(path)),
+        other => other.clone(),
+    };
+    let output_path = output_file.as_deref();
+    // Extensions compare case-insensitively, so `rs` also picks up `main.RS`
+    let lowercase =
+        |exts: &[String]| -> Vec<String> { exts.iter().map(|e| e.to_lowercase()).collect() };
+    let include = lowercase(&options.include);
+    let exclude = lowercase(&options.exclude);
+    let max_file_size = options.max_file_size;
+    let max_tokens = options.max_tokens;
 
     if !root.exists() || !root.is_dir() {
-        eprintln!("❌ Error: Invalid directory path: {:?}", root);
-        return;
+        return Err(format!("Invalid directory path: {:?}", root));
     }
 
-    let mut output_writer: Box<dyn Write> = match output_path {
+    let tokenizer = make_tokenizer(options.tokenizer)?;
+
+    // Clipboard mode renders into memory and hands the result over at the end
+    let use_clipboard = options.clipboard && output_path.is_none();
+    let (uncompressed, compressed) = (Cell::new(0), Cell::new(0));
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut output_writer: Box<dyn Write + '_> = match output_path {
+        _ if options.stats_only => Box::new(io::sink()),
         Some(path) => {
-            println!("📝 Combining code from {:?} into {:?}", root, path);
-            match fs::File::create(path) {
-                Ok(file) => Box::new(file),
-                Err(e) => {
-                    eprintln!("❌ Error creating output file: {}", e);
-                    return;
-                }
+            if !options.quiet {
+                say!("📝 Combining code from {:?} into {:?}", root, path);
             }
+            let file = fs::File::create(path)
+                .map_err(|e| format!("Could not create output file: {}", e))?;
+            Box::new(BufWriter::new(file))
         }
+        None if use_clipboard => Box::new(&mut buffer),
         None => Box::new(std::io::stdout()),
     };
 
+    if gzip {
+        let encoder = GzEncoder::new(
+            Counter {
+                inner: output_writer,
+                count: &compressed,
+            },
+            Compression::default(),
+        );
+        output_writer = Box::new(Counter {
+            inner: encoder,
+            count: &uncompressed,
+        });
+    }
+
     let ignored_folders = [
         "node_modules",
         "target",
@@ -50,71 +931,144 @@ pub fn combine_code(
         "__pycache__",
     ];
 
-    let ignored_files = [
-        "package-lock.json",
-        "yarn.lock",
-        "pnpm-lock.yaml",
-        "Cargo.lock",
-        "composer.lock",
-        ".DS_Store",
-        "Thumbs.db",
-        ".env",
-    ];
+    let mut ignored_files: Vec<&str> = match &options.ignored_files {
+        Some(names) => names.iter().map(String::as_str).collect(),
+        None => LOCKFILES.iter().chain(JUNK_FILES).copied().collect(),
+    };
+    if options.include_lockfiles {
+        ignored_files.retain(|name| !LOCKFILES.contains(name));
+    }
 
-    let walker = WalkDir::new(root).into_iter().filter_entry(|e| {
-        let name = e.file_name().to_str().unwrap_or("");
+    let project_ignore = load_project_ignore(root)?;
 
-        // Always enter the root directory
-        if e.depth() == 0 {
-            return true;
-        }
+    // Paths piped in with --stdin are explicit files, bypassing the walk and its filters
+    let piped: Vec<String> = if options.stdin {
+        io::stdin()
+            .lock()
+            .lines()
+            .map_while(Result::ok)
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    for path in piped.iter().filter(|p| !Path::new(p).is_file()) {
+        term::warning(format!("Skipping {:?}: not a file", path));
+    }
 
-        if name.starts_with('.') {
-            return false;
-        }
+    // Explicitly named files are taken as-is, relative to the root; anything else is a glob over the walk
+    let (literal, patterns): (Vec<&String>, Vec<&String>) =
+        options.paths.iter().partition(|p| root.join(p).is_file());
+    let mut literal: Vec<PathBuf> = literal.into_iter().map(|p| root.join(p)).collect();
+    literal.extend(piped.iter().map(PathBuf::from).filter(|p| p.is_file()));
+    let selection = build_selection(&patterns)?;
+    let explicit = !options.paths.is_empty() || options.stdin;
+    // .gitignore/.ignore rules apply even outside a git checkout; hidden entries are always skipped.
+    // Globs the user named themselves reach into ignored, hidden and build folders too.
+    // .rs-cleanignore applies even with --no-ignore, since it only exists for this tool.
+    let walker = WalkBuilder::new(root)
+        .standard_filters(!options.no_ignore && !explicit)
+        .hidden(!explicit)
+        .require_git(false)
+        .filter_entry(move |e| {
+            let name = e.file_name().to_str().unwrap_or("");
 
-        if e.file_type().is_dir() {
-            return !ignored_folders.contains(&name);
-        }
+            // Always enter the root directory
+            if e.depth() == 0 {
+                return true;
+            }
+
+            let is_dir = e.file_type().is_some_and(|t| t.is_dir());
+            if project_ignore
+                .matched_path_or_any_parents(e.path(), is_dir)
+                .is_ignore()
+            {
+                return false;
+            }
+
+            if is_dir && !explicit {
+                return !ignored_folders.contains(&name);
+            }
+
+            true
+        })
+        .build();
 
-        true
-    });
+    let path_filters =
+        build_selection(&options.include_paths.iter().collect::<Vec<_>>()).and_then(|include| {
+            let exclude = build_selection(&options.exclude_paths.iter().collect::<Vec<_>>())?;
+            Ok((include, exclude))
+        });
+    let (include_paths, exclude_paths) = path_filters?;
 
-    let mut total_files = 0;
-    let mut total_size = 0;
-    let mut total_tokens = 0;
+    // Each file is combined once, however many times it was named
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    let mut files: Vec<(PathBuf, u64)> = literal
+        .into_iter()
+        .filter(|p| p.canonicalize().is_ok_and(|c| seen.insert(c)))
+        .map(|p| {
+            let size = fs::metadata(&p).map(|m| m.len()).unwrap_or(0);
+            (p, size)
+        })
+        .collect();
+    // Resolved once; only entries sharing its file name are canonicalized to compare
+    let output_canonical = output_path.and_then(canonical_output);
+    let is_output = |path: &Path| {
+        output_canonical.as_deref().is_some_and(|out| {
+            path.file_name() == out.file_name() && path.canonicalize().is_ok_and(|p| p == out)
+        })
+    };
 
     for entry in walker.filter_map(|e| e.ok()) {
+        // Only literal files were given, nothing to walk for
+        if explicit && patterns.is_empty() {
+            break;
+        }
         let path = entry.path();
 
         if path.is_file() {
-            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            // Skip the output file itself if it's in the list
+            if is_output(path) {
+                continue;
+            }
 
-            // 1. Skip ignored files
-            if ignored_files.contains(&file_name) || file_name.starts_with('.') {
+            let rel_path = path.strip_prefix(root).unwrap_or(path);
+            if matches_path(&exclude_paths, rel_path) {
                 continue;
             }
 
-            // Skip the output file itself if it's in the list
-            if let Some(out_path) = output_path {
-                if let Ok(canon_p) = path.canonicalize() {
-                    if let Ok(canon_out) = out_path.canonicalize() {
-                        if canon_p == canon_out {
-                            continue;
-                        }
-                    }
+            // Files picked by glob skip the extension and name filters
+            if explicit {
+                if selection.is_match(rel_path) && path.canonicalize().is_ok_and(|p| seen.insert(p))
+                {
+                    let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                    files.push((path.to_path_buf(), size));
                 }
+                continue;
+            }
+
+            if !options.include_paths.is_empty() && !matches_path(&include_paths, rel_path) {
+                continue;
+            }
+
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+            // 1. Skip ignored files
+            if ignored_files.contains(&file_name) || file_name.starts_with('.') {
+                continue;
             }
 
             // 2. Check extensions
             if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
+                let ext = ext.to_lowercase();
                 // If specific includes are set, must match one of them
-                if !include.is_empty() && !include.contains(&ext.to_string()) {
+                if !include.is_empty() && !include.contains(&ext) {
                     continue;
                 }
 
                 // If in exclude list, skip
-                if exclude.contains(&ext.to_string()) {
+                if exclude.contains(&ext) {
                     continue;
                 }
 
@@ -123,56 +1077,414 @@ pub fn combine_code(
                     "png", "jpg", "jpeg", "gif", "ico", "svg", "woff", "woff2", "ttf", "eot",
                     "mp4", "webm", "zip", "tar", "gz", "exe", "dll", "so", "dylib", "class", "pyc",
                 ];
-                if skip_exts.contains(&ext) {
+                if skip_exts.contains(&ext.as_str()) {
                     continue;
                 }
             } else {
                 // No extension? usually skip unless user specifically asked for it via include (handled above)
                 // or if include is empty, we might skip to be safe, or include simple text files like LICENSE, Makefile
                 let known_text_files = ["Makefile", "Dockerfile", "LICENSE", "README"];
-                let is_known = known_text_files.iter().any(|f| file_name.ends_with(f)); // rough check
+                let is_known = known_text_files
+                    .iter()
+                    .any(|f| file_name.to_lowercase().ends_with(&f.to_lowercase())); // rough check
 
                 if !include.is_empty() && !is_known {
                     continue;
                 }
             }
 
-            // 3. Read and Append
-            match fs::read_to_string(path) {
-                Ok(content) => {
-                    // Try to make path relative to root for cleaner headers
-                    let rel_path = path.strip_prefix(root).unwrap_or(path);
-                    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-                    let size = content.len();
-                    let tokens = estimate_tokens(&content);
-
-                    total_files += 1;
-                    total_size += size as u64;
-                    total_tokens += tokens;
-
-                    let header = format!("\n# File: {}\n```{}\n", rel_path.display(), ext);
-                    let footer = "\n```\n";
-
-                    if let Err(e) = output_writer
-                        .write_all(header.as_bytes())
-                        .and_then(|_| output_writer.write_all(content.as_bytes()))
-                        .and_then(|_| output_writer.write_all(footer.as_bytes()))
-                    {
-                        eprintln!("❌ Error writing to output: {}", e);
-                    }
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            files.push((path.to_path_buf(), size));
+        }
+    }
+
+    // Content is the final word on binaries: the extension list only catches the obvious ones
+    let (binary, mut files): (Vec<_>, Vec<_>) = files
+        .into_par_iter()
+        .partition(|(path, _)| looks_binary(path));
+
+    // Sorted by path so the output doesn't depend on walk or thread timing. With a token
+    // budget, shallow and small files go first so the most central code survives, unless
+    // --sort asks for a specific order. The sorts are stable, so ties stay in path order.
+    files.sort();
+    match options.sort {
+        Some(SortOrder::Name) => {}
+        Some(SortOrder::Size) => files.sort_by_key(|(_, size)| *size),
+        Some(SortOrder::Ext) => files.sort_by(|(a, _), (b, _)| a.extension().cmp(&b.extension())),
+        None if max_tokens.is_some() => {
+            files.sort_by_key(|(path, size)| (path.components().count(), *size))
+        }
+        None => {}
+    }
+
+    let mut formatter: Box<dyn Formatter> = match options.format {
+        _ if options.list => Box::new(List {
+            preview: options.preview.unwrap_or(0),
+            ..List::default()
+        }),
+        CombineFormat::Markdown => Box::new(Markdown {
+            template: make_template(options.template),
+            line_numbers: options.line_numbers,
+            width: 1,
+            next_line: 1,
+        }),
+        CombineFormat::Json => Box::new(Json::default()),
+    };
+    let mut summary = CombineSummary {
+        files: 0,
+        total_bytes: 0,
+        lines: 0,
+        tokens: 0,
+        tokenizer: tokenizer.name(),
+        skipped_large: Vec::new(),
+        skipped_binary: binary
+            .iter()
+            .map(|(path, _)| {
+                path.strip_prefix(root)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string()
+            })
+            .collect(),
+        dropped: Vec::new(),
+    };
+
+    // The tree lists every candidate, so files later omitted or dropped still show up
+    if !options.no_tree || options.list {
+        let tree = render_tree(
+            files
+                .iter()
+                .map(|(path, _)| path.strip_prefix(root).unwrap_or(path)),
+        );
+        if let Err(e) = formatter.write_tree(&mut output_writer, &tree) {
+            term::error(format!("Could not write output: {}", e));
+        }
+    }
+
+    // The bar is drawn on stderr, and only when stdout isn't carrying the combined output
+    let show_progress = (output_path.is_some() || use_clipboard || options.stats_only)
+        && !options.quiet
+        && io::stderr().is_terminal();
+    let progress = if show_progress {
+        let style = ProgressStyle::with_template("{bar:30} {pos}/{len} files | {msg}")
+            .unwrap_or_else(|_| ProgressStyle::default_bar());
+        ProgressBar::new(files.len() as u64).with_style(style)
+    } else {
+        ProgressBar::hidden()
+    };
+    let running_tokens = AtomicUsize::new(0);
+
+    // Count a batch in parallel, then stream its files in order: only one chunk of content
+    // is held at a time, and files after the token budget runs out are never read
+    let batch_len = rayon::current_num_threads() * COUNT_BATCH_PER_THREAD;
+    for batch in files.chunks(batch_len) {
+        // Once the budget is spent, everything left is dropped
+        if !summary.dropped.is_empty() {
+            summary.dropped.extend(batch.iter().map(|(path, _)| {
+                path.strip_prefix(root)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string()
+            }));
+            progress.inc(batch.len() as u64);
+            continue;
+        }
+
+        // `collect` keeps the results in the order of `batch`
+        let counted: Vec<Option<io::Result<FileCounts>>> = batch
+            .par_iter()
+            .map(|(path, size)| {
+                let oversized = max_file_size.is_some_and(|limit| *size > limit.as_u64());
+                let counted = (!oversized).then(|| count_file(path, &*tokenizer));
+                if let Some(Ok(counts)) = &counted {
+                    running_tokens.fetch_add(counts.tokens, Ordering::Relaxed);
                 }
-                Err(_) => {
-                    // Likely binary or non-utf8, skip silently
+                progress.set_message(format!(
+                    "{} | ~{} tokens",
+                    path.strip_prefix(root).unwrap_or(path).display(),
+                    running_tokens.load(Ordering::Relaxed)
+                ));
+                progress.inc(1);
+                counted
+            })
+            .collect();
+
+        for ((path, size), counted) in batch.iter().zip(counted) {
+            let rel_path = path.strip_prefix(root).unwrap_or(path);
+
+            if !summary.dropped.is_empty() {
+                summary.dropped.push(rel_path.display().to_string());
+                continue;
+            }
+
+            // 3. Skip oversized files, leaving a placeholder so the omission is visible
+            if let Some(limit) = max_file_size
+                && *size > limit.as_u64()
+            {
+                let skipped = SkippedFile {
+                    path: rel_path.display().to_string(),
+                    size: *size,
+                };
+                if let Err(e) = formatter.write_omitted(&mut output_writer, &skipped, limit) {
+                    term::error(format!("Could not write output: {}", e));
                 }
+                summary.skipped_large.push(skipped);
+                continue;
+            }
+
+            let counts = match counted {
+                Some(Ok(counts)) => counts,
+                Some(Err(e)) => {
+                    term::error(format!("Could not read {:?}: {}", rel_path, e));
+                    continue;
+                }
+                None => continue,
+            };
+
+            // 4. Drop the file if it would overrun the token budget
+            if let Some(budget) = max_tokens
+                && summary.tokens + counts.tokens > budget
+            {
+                summary.dropped.push(rel_path.display().to_string());
+                continue;
+            }
+
+            // 5. Stream the file into the output
+            let result = formatter
+                .begin_file(
+                    &mut output_writer,
+                    &rel_path.display().to_string(),
+                    &language_for(path),
+                    counts.lines,
+                )
+                .and_then(|_| {
+                    if !options.list {
+                        return read_chunks(path, |chunk| {
+                            formatter.write_chunk(&mut output_writer, chunk)
+                        });
+                    }
+                    // A listing shows no more than the preview, so read no further
+                    BufReader::new(fs::File::open(path)?)
+                        .lines()
+                        .take(options.preview.unwrap_or(0))
+                        .try_for_each(|line| {
+                            formatter.write_chunk(&mut output_writer, &format!("{}\n", line?))
+                        })
+                })
+                .and_then(|_| formatter.end_file(&mut output_writer, counts.size, counts.tokens));
+            if let Err(e) = result {
+                term::error(format!("Could not combine {:?}: {}", rel_path, e));
+            }
+
+            summary.files += 1;
+            summary.total_bytes += counts.size;
+            summary.lines += counts.lines;
+            summary.tokens += counts.tokens;
+        }
+    }
+    progress.finish_and_clear();
+
+    formatter
+        .finish(&mut output_writer, &summary)
+        .and_then(|_| output_writer.flush())
+        .map_err(|e| format!("Could not write output: {}", e))?;
+    // Dropping the encoder writes the gzip trailer
+    drop(output_writer);
+
+    let mut copied = false;
+    if use_clipboard {
+        let text = String::from_utf8_lossy(&buffer).into_owned();
+        match copy_to_clipboard(text.clone()) {
+            Ok(()) => {
+                say!("📋 Copied combined output to the clipboard.");
+                copied = true;
+            }
+            Err(e) => {
+                term::warning(format!(
+                    "Clipboard unavailable ({}), printing to stdout instead.",
+                    e
+                ));
+                print!("{}", text);
+            }
+        }
+    }
+
+    if (output_path.is_some() || copied || options.stats_only) && !options.quiet {
+        if !options.stats_only {
+            say!(
+                "{}",
+                term::paint("✅ Successfully combined code.", Color::Green)
+            );
+        }
+        say!("📊 Stats:");
+        say!("   Files: {}", summary.files);
+        say!("   Lines: {}", summary.lines);
+        say!("   Total Size: {}", ByteSize(summary.total_bytes));
+        say!("   Est. Tokens: {} ({})", summary.tokens, summary.tokenizer);
+        if gzip {
+            say!(
+                "   Output: {} ({} gzipped)",
+                ByteSize(uncompressed.get()),
+                ByteSize(compressed.get())
+            );
+        }
+        if !summary.skipped_binary.is_empty() {
+            say!("   Skipped (binary): {}", summary.skipped_binary.len());
+        }
+        if !summary.skipped_large.is_empty() {
+            say!("   Skipped (too large): {}", summary.skipped_large.len());
+            for file in &summary.skipped_large {
+                say!("      {} ({})", file.path, ByteSize(file.size));
+            }
+        }
+        if !summary.dropped.is_empty() {
+            say!(
+                "   Dropped (over --max-tokens {}): {}",
+                max_tokens.unwrap_or(0),
+                summary.dropped.len()
+            );
+            for path in &summary.dropped {
+                say!("      {}", path);
             }
         }
     }
 
-    if output_path.is_some() {
-        println!("✅ Successfully combined code.");
-        println!("📊 Stats:");
-        println!("   Files: {}", total_files);
-        println!("   Total Size: {}", ByteSize(total_size).to_string());
-        println!("   Est. Tokens: {} (Heuristic: chars/4)", total_tokens);
+    Ok(CombineStats {
+        files: summary.files,
+        total_bytes: summary.total_bytes,
+        lines: summary.lines,
+        tokens: summary.tokens,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Combine `root` as JSON, returning each combined file's path and language.
+    fn combined(root: &Path, options: CombineOptions) -> Vec<(String, String)> {
+        let output = root.with_extension("json");
+        let options = CombineOptions {
+            output: Some(output.clone()),
+            format: CombineFormat::Json,
+            no_tree: true,
+            quiet: true,
+            ..options
+        };
+        combine_code(root, &options).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+        fs::remove_file(output).unwrap();
+        json["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| {
+                (
+                    f["path"].as_str().unwrap().to_string(),
+                    f["language"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn extensions_match_case_insensitively() {
+        let root = std::env::temp_dir().join(format!("rs-clean-test-{}-ext", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        for name in ["main.PY", "lib.Rs", "app.js", "notes.TXT"] {
+            fs::write(root.join(name), "text\n").unwrap();
+        }
+
+        let included = combined(
+            &root,
+            CombineOptions {
+                include: vec!["py".to_string(), "rs".to_string()],
+                ..CombineOptions::default()
+            },
+        );
+        let excluded = combined(
+            &root,
+            CombineOptions {
+                exclude: vec!["TXT".to_string(), "Js".to_string()],
+                ..CombineOptions::default()
+            },
+        );
+        fs::remove_dir_all(&root).unwrap();
+
+        let expected = [
+            ("lib.Rs".to_string(), "rust".to_string()),
+            ("main.PY".to_string(), "python".to_string()),
+        ];
+        assert_eq!(included, expected);
+        assert_eq!(excluded, expected);
+    }
+
+    #[test]
+    fn named_paths_resolve_against_the_root_and_skip_the_walk_filters() {
+        let root = std::env::temp_dir().join(format!("rs-clean-test-{}-paths", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for dir in ["target", ".hidden", "src"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for name in [
+            "notes.txt",
+            "target/gen.rs",
+            ".hidden/secret.rs",
+            "src/lib.rs",
+            "skip.rs",
+        ] {
+            fs::write(root.join(name), "text\n").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "skip.rs\n").unwrap();
+
+        let mut files = combined(
+            &root,
+            CombineOptions {
+                paths: ["notes.txt", "target/*.rs", ".hidden/*.rs", "*.rs"]
+                    .map(String::from)
+                    .to_vec(),
+                ..CombineOptions::default()
+            },
+        );
+        fs::remove_dir_all(&root).unwrap();
+
+        files.sort();
+        let paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [".hidden/secret.rs", "notes.txt", "skip.rs", "target/gen.rs"]
+        );
+    }
+
+    #[test]
+    fn sniffing_tells_binary_from_text_whatever_the_extension() {
+        let root = std::env::temp_dir().join(format!("rs-clean-test-{}-sniff", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        // A three-byte character straddling the end of the sniffed sample
+        let mut straddling = "a".repeat(SNIFF_LEN - 1).into_bytes();
+        straddling.extend("€ more text\n".as_bytes());
+        let files: [(&str, &[u8]); 5] = [
+            ("notes.rs", b"fn main() {}\n"),
+            ("blob.rs", b"fn main() {}\0\x01\x02"),
+            ("latin1.txt", b"caf\xe9\n"),
+            ("data.custom", b"key = value\n"),
+            ("long.rs", &straddling),
+        ];
+        for (name, content) in files {
+            fs::write(root.join(name), content).unwrap();
+        }
+
+        let binary: Vec<bool> = files
+            .iter()
+            .map(|(name, _)| looks_binary(&root.join(name)))
+            .collect();
+        let missing = looks_binary(&root.join("missing.rs"));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(binary, [false, true, true, false, false]);
+        assert!(missing);
     }
 }
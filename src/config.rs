@@ -0,0 +1,66 @@
+use crate::tui::{ThemeColors, ThemeName};
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+
+const CONFIG_FILE_NAME: &str = "rs-clean.toml";
+
+/// Defaults for command-line flags; anything passed on the command line wins.
+///
+/// Read from `rs-clean.toml` in the working directory, falling back to
+/// `$XDG_CONFIG_HOME/rs-clean/config.toml`. The same `rs-clean.toml` may also
+/// hold `[[rules]]` for the clean command.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    pub clean: CleanDefaults,
+    pub combine: CombineDefaults,
+    pub tui: TuiDefaults,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct CleanDefaults {
+    pub threads: Option<usize>,
+    pub respect_gitignore: bool,
+    pub hidden: bool,
+    pub max_depth: Option<usize>,
+    pub exclude: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct CombineDefaults {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Replaces the built-in list of file names combine-code skips
+    pub ignored_files: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct TuiDefaults {
+    pub theme: Option<ThemeName>,
+    pub colors: ThemeColors,
+}
+
+/// The first config file that exists, if any.
+fn config_path() -> Option<PathBuf> {
+    let local = PathBuf::from(CONFIG_FILE_NAME);
+    if local.is_file() {
+        return Some(local);
+    }
+    dirs::config_dir()
+        .map(|dir| dir.join("rs-clean").join("config.toml"))
+        .filter(|p| p.is_file())
+}
+
+/// Load the defaults config, or an empty one when no file exists.
+pub fn load() -> Result<Config, String> {
+    let Some(path) = config_path() else {
+        return Ok(Config::default());
+    };
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read config {:?}: {}", path, e))?;
+    toml::from_str(&text).map_err(|e| format!("Invalid config {:?}: {}", path, e))
+}
@@ -0,0 +1,24 @@
+//! Find and remove dependency and build folders (`node_modules`, `target`, …), and bundle
+//! source trees into a single document.
+//!
+//! The `rs-clean` binary is a thin command-line layer over this crate. For disk analysis
+//! from other tools, start with [`Scanner`]:
+//!
+//! ```no_run
+//! use rs_clean::Scanner;
+//!
+//! let report = Scanner::new("/home/me/code").older_than(30).scan()?;
+//! println!("{} bytes reclaimable", report.reclaimable_bytes());
+//! # Ok::<(), rs_clean::clean::CleanError>(())
+//! ```
+
+mod cache;
+pub mod clean;
+pub mod combine;
+pub mod config;
+pub mod manifest;
+mod scanner;
+pub mod term;
+pub mod tui;
+
+pub use scanner::{ScanReport, Scanner};
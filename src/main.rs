@@ -1,13 +1,26 @@
-mod clean;
 mod cli;
-mod combine;
-mod tui;
 
 use clap::Parser;
 use cli::{Cli, Commands};
+use rs_clean::{clean, combine, config, manifest, term, tui};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    #[cfg(target_os = "linux")]
+    if std::env::args().nth(1).as_deref() == Some(combine::CLIPBOARD_ARG) {
+        return combine::serve_clipboard();
+    }
 
-fn main() {
     let cli = Cli::parse();
+    term::init(cli.no_color, cli.ascii);
+
+    let defaults = match config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            term::error(e);
+            return ExitCode::FAILURE;
+        }
+    };
 
     match &cli.command {
         Commands::Version => {
@@ -18,21 +31,226 @@ fn main() {
                 println!("Hello {}!", name);
             }
         }
-        Commands::Clean { path, force } => {
-            clean::clean_projects(path, *force);
+        Commands::Clean {
+            paths,
+            dry_run: _,
+            delete,
+            force,
+            older_than,
+            newer_than,
+            min_size,
+            trash,
+            free_up,
+            fail_if_over,
+            category,
+            indicator_search_depth,
+            ignore_indicator,
+            sequential,
+            watch,
+            daemon,
+            interval,
+            force_busy,
+            prune_empty_parents,
+            skip_dirty,
+            max_matches,
+            yes,
+            i_know_what_im_doing,
+            interactive,
+            config,
+            no_default_rules,
+            only,
+            skip,
+            format,
+            report,
+            log,
+            on_complete,
+            quiet,
+            summary_only_on_change,
+            verbose,
+            exclude,
+            protect,
+            respect_gitignore,
+            no_respect_gitignore,
+            hidden,
+            no_hidden,
+            parents,
+            follow_symlinks,
+            threads,
+            max_depth,
+            size_timeout,
+            cache,
+            on_disk,
+            top,
+            no_progress,
+            no_eta,
+        } => {
+            if *force {
+                term::warning("--force is deprecated, use --delete instead.");
+            }
+            let mode = if *delete || *force {
+                clean::Mode::Delete
+            } else {
+                clean::Mode::DryRun
+            };
+            let options = clean::CleanOptions {
+                mode,
+                older_than: *older_than,
+                newer_than: *newer_than,
+                min_size: *min_size,
+                interactive: *interactive,
+                config: config.clone(),
+                no_default_rules: *no_default_rules,
+                rules: None,
+                only: only.clone(),
+                skip: skip.clone(),
+                format: *format,
+                report: report.clone(),
+                log: log.clone(),
+                on_complete: on_complete.clone(),
+                silent_when_clean: *summary_only_on_change,
+                verbosity: if *quiet || *summary_only_on_change {
+                    clean::Verbosity::Quiet
+                } else if *verbose {
+                    clean::Verbosity::Verbose
+                } else {
+                    clean::Verbosity::Normal
+                },
+                exclude: if exclude.is_empty() {
+                    defaults.clean.exclude.clone()
+                } else {
+                    exclude.clone()
+                },
+                protect: protect.clone(),
+                respect_gitignore: !*no_respect_gitignore
+                    && (*respect_gitignore || defaults.clean.respect_gitignore),
+                hidden: !*no_hidden && (*hidden || defaults.clean.hidden),
+                parents: *parents,
+                max_depth: max_depth.or(defaults.clean.max_depth),
+                force_busy: *force_busy,
+                cache: *cache,
+                on_disk: *on_disk,
+                top: *top,
+                progress: !*no_progress
+                    && matches!(
+                        format,
+                        clean::OutputFormat::Human | clean::OutputFormat::Table
+                    ),
+                eta: !*no_eta,
+                trash: *trash,
+                free_up: *free_up,
+                fail_if_over: *fail_if_over,
+                categories: category.clone(),
+                indicator_search_depth: *indicator_search_depth,
+                ignore_indicator: *ignore_indicator,
+                follow_symlinks: *follow_symlinks,
+                sequential: *sequential,
+                prune_empty_parents: *prune_empty_parents,
+                skip_dirty: *skip_dirty,
+                size_timeout: size_timeout.map(std::time::Duration::from_secs),
+                max_matches: (!*yes).then_some(*max_matches),
+                allow_broad_root: *i_know_what_im_doing,
+                threads: threads
+                    .map(|n| n.get())
+                    .or(sequential.then_some(1))
+                    .or(defaults.clean.threads.filter(|&n| n > 0)),
+            };
+            if *watch {
+                return clean::watch_projects(paths, &options);
+            }
+            if *daemon {
+                return clean::run_daemon(
+                    paths,
+                    &options,
+                    std::time::Duration::from_secs(*interval),
+                );
+            }
+            return clean::clean_projects(paths, &options);
         }
         Commands::CombineCode {
+            paths,
             path,
+            stdin,
             output,
             include,
             exclude,
+            include_path,
+            exclude_path,
+            include_lockfiles,
+            no_ignore,
+            max_file_size,
+            max_tokens,
+            tokenizer,
+            format,
+            template,
+            sort,
+            no_tree,
+            line_numbers,
+            clipboard,
+            stats_only,
+            gzip,
+            list,
+            preview,
         } => {
-            combine::combine_code(path, output.as_deref(), include, exclude);
+            let options = combine::CombineOptions {
+                paths: paths.clone(),
+                stdin: *stdin,
+                quiet: false,
+                output: output.clone(),
+                include: if include.is_empty() {
+                    defaults.combine.include.clone()
+                } else {
+                    include.clone()
+                },
+                exclude: if exclude.is_empty() {
+                    defaults.combine.exclude.clone()
+                } else {
+                    exclude.clone()
+                },
+                include_paths: include_path.clone(),
+                exclude_paths: exclude_path.clone(),
+                ignored_files: defaults.combine.ignored_files.clone(),
+                include_lockfiles: *include_lockfiles,
+                no_ignore: *no_ignore,
+                max_file_size: *max_file_size,
+                max_tokens: *max_tokens,
+                tokenizer: *tokenizer,
+                format: *format,
+                template: *template,
+                sort: *sort,
+                no_tree: *no_tree,
+                line_numbers: *line_numbers,
+                clipboard: *clipboard,
+                stats_only: *stats_only,
+                gzip: *gzip,
+                list: *list || preview.is_some(),
+                preview: *preview,
+            };
+            if let Err(e) = combine::combine_code(path, &options) {
+                term::error(e);
+                return ExitCode::FAILURE;
+            }
         }
-        Commands::Tui => {
-            if let Err(e) = tui::run_tui() {
-                eprintln!("Error running TUI: {}", e);
+        Commands::Restore => {
+            if let Err(e) = manifest::restore_latest() {
+                term::error(e);
+                return ExitCode::FAILURE;
+            }
+        }
+        Commands::Tui { theme } => {
+            let name = theme.or(defaults.tui.theme).unwrap_or_default();
+            let theme = match tui::Theme::preset(name).with_overrides(&defaults.tui.colors) {
+                Ok(theme) => theme,
+                Err(e) => {
+                    term::error(e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            if let Err(e) = tui::run_tui(theme) {
+                term::error(format!("Could not run the TUI: {}", e));
+                return ExitCode::FAILURE;
             }
         }
     }
+
+    ExitCode::SUCCESS
 }
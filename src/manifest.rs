@@ -0,0 +1,234 @@
+use crate::term::{self, say};
+use crossterm::style::Color;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MANIFEST_PREFIX: &str = "manifest-";
+
+/// One folder moved to the trash by `clean --trash`.
+#[derive(Serialize, Deserialize)]
+struct ManifestEntry {
+    original: PathBuf,
+    /// System specific trash identifier (the `.trashinfo` path on Linux)
+    trash_id: String,
+}
+
+/// Record of a single `clean --trash` run, used by `restore` to undo it.
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    created: u64,
+    entries: Vec<ManifestEntry>,
+}
+
+/// Directory holding manifests, e.g. `~/.cache/rs-clean/`.
+fn manifest_dir() -> Result<PathBuf, String> {
+    dirs::cache_dir()
+        .map(|dir| dir.join("rs-clean"))
+        .ok_or_else(|| "Could not determine the user cache directory.".to_string())
+}
+
+/// The newest manifest in the cache dir, by the timestamp in its file name.
+fn latest_manifest(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let stamp = name
+                .strip_prefix(MANIFEST_PREFIX)?
+                .strip_suffix(".json")?
+                .parse::<u64>()
+                .ok()?;
+            Some((stamp, entry.path()))
+        })
+        .max_by_key(|(stamp, _)| *stamp)
+        .map(|(_, path)| path)
+}
+
+/// A directory that is missing or empty can safely receive a restored folder.
+fn is_vacant(path: &Path) -> bool {
+    match fs::read_dir(path) {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(_) => !path.exists(),
+    }
+}
+
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+mod platform {
+    use super::*;
+    use trash::os_limited;
+
+    pub fn trash_ids() -> HashSet<String> {
+        os_limited::list()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| item.id.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Find the trash items created since `before` for each of `trashed`, which must be
+    /// canonical paths like the ones the trash records.
+    pub fn new_entries(before: &HashSet<String>, trashed: &[PathBuf]) -> Vec<ManifestEntry> {
+        os_limited::list()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|item| !before.contains(item.id.to_string_lossy().as_ref()))
+            .filter(|item| trashed.contains(&item.original_path()))
+            .map(|item| ManifestEntry {
+                original: item.original_path(),
+                trash_id: item.id.to_string_lossy().into_owned(),
+            })
+            .collect()
+    }
+
+    pub fn restore(entry: &ManifestEntry) -> Result<(), String> {
+        let item = os_limited::list()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .find(|item| item.id.to_string_lossy() == entry.trash_id)
+            .ok_or_else(|| "no longer in the trash".to_string())?;
+        os_limited::restore_all([item]).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+mod platform {
+    use super::*;
+
+    pub fn trash_ids() -> HashSet<String> {
+        HashSet::new()
+    }
+
+    pub fn new_entries(_before: &HashSet<String>, _trashed: &[PathBuf]) -> Vec<ManifestEntry> {
+        Vec::new()
+    }
+
+    pub fn restore(_entry: &ManifestEntry) -> Result<(), String> {
+        Err("restoring from the trash is not supported on this platform".to_string())
+    }
+}
+
+/// Snapshot of the trash contents, taken before trashing so new items can be told apart.
+pub fn trash_snapshot() -> HashSet<String> {
+    platform::trash_ids()
+}
+
+/// Write a manifest for the folders in `trashed` (canonicalized before they were trashed),
+/// returning its path.
+pub fn write_manifest(before: &HashSet<String>, trashed: &[PathBuf]) -> Result<PathBuf, String> {
+    let entries = platform::new_entries(before, trashed);
+    if entries.is_empty() {
+        return Err("Could not locate the trashed folders to record a manifest.".to_string());
+    }
+
+    let dir = manifest_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Could not create {:?}: {}", dir, e))?;
+
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = dir.join(format!("{}{}.json", MANIFEST_PREFIX, created));
+    let manifest = Manifest { created, entries };
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Could not write {:?}: {}", path, e))?;
+    Ok(path)
+}
+
+/// Move every folder from the most recent manifest back to its original location.
+///
+/// Fails if the manifest can't be read or any folder could not be restored.
+pub fn restore_latest() -> Result<(), String> {
+    let dir = manifest_dir()?;
+    let Some(path) = latest_manifest(&dir) else {
+        say!("✨ Nothing to restore.");
+        return Ok(());
+    };
+
+    let manifest: Manifest = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()))
+        .map_err(|e| format!("Could not read manifest {:?}: {}", path, e))?;
+
+    say!(
+        "♻️  Restoring {} folder(s) from {:?}\n",
+        manifest.entries.len(),
+        path
+    );
+
+    let mut restored = 0;
+    let mut failed = 0;
+    let mut conflicts = Vec::new();
+    for entry in manifest.entries {
+        if !is_vacant(&entry.original) {
+            say!(
+                "⚠️  Conflict: {:?} already exists and is not empty",
+                entry.original
+            );
+            conflicts.push(entry);
+            continue;
+        }
+        // An empty directory may have been recreated by a build tool; the restore replaces it
+        let _ = fs::remove_dir(&entry.original);
+
+        match platform::restore(&entry) {
+            Ok(()) => {
+                restored += 1;
+                say!(
+                    "{}",
+                    term::paint(format!("✅ Restored {:?}", entry.original), Color::Green)
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                say!("   FAILED to restore {:?}: {}", entry.original, e);
+            }
+        }
+    }
+
+    let conflict_count = conflicts.len();
+
+    // Keep conflicting entries around so the restore can be retried once they're resolved
+    let result = if conflicts.is_empty() {
+        fs::remove_file(&path)
+    } else {
+        let manifest = Manifest {
+            created: manifest.created,
+            entries: conflicts,
+        };
+        serde_json::to_string_pretty(&manifest)
+            .map_err(std::io::Error::other)
+            .and_then(|json| fs::write(&path, json))
+    };
+    if let Err(e) = result {
+        term::error(format!("Could not update manifest {:?}: {}", path, e));
+    }
+
+    say!("\n📋 Restored: {}, Conflicts: {}", restored, conflict_count);
+    if failed + conflict_count > 0 {
+        return Err(format!(
+            "{} folder(s) could not be restored.",
+            failed + conflict_count
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,270 @@
+use crate::clean::{
+    self, Category, CleanError, CleanMatch, CleanOptions, CleanRule, DeleteReport, Mode,
+    RuleProtect,
+};
+use bytesize::ByteSize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Builder for a scan of one or more roots, the library counterpart of `rs-clean clean`.
+///
+/// Every setter maps onto the `clean` flag of the same name; anything left unset behaves
+/// like the flag being omitted.
+///
+/// ```
+/// use rs_clean::Scanner;
+/// # let root = std::env::temp_dir().join("rs-clean-doc-scanner");
+/// # let _ = std::fs::remove_dir_all(&root);
+/// # std::fs::create_dir_all(root.join("app/node_modules/left-pad")).unwrap();
+/// # std::fs::write(root.join("app/package.json"), "{}").unwrap();
+/// # std::fs::write(root.join("app/node_modules/left-pad/index.js"), "module.exports = 1;").unwrap();
+///
+/// let report = Scanner::new(&root)
+///     .max_depth(3)
+///     .exclude(["vendored/"])
+///     .scan()?;
+///
+/// for m in report.cleanable() {
+///     println!("{} ({}): {} bytes", m.path.display(), m.rule, m.size_bytes);
+/// }
+/// assert_eq!(report.cleanable().count(), 1);
+/// # std::fs::remove_dir_all(&root).unwrap();
+/// # Ok::<(), rs_clean::clean::CleanError>(())
+/// ```
+pub struct Scanner {
+    roots: Vec<PathBuf>,
+    options: CleanOptions,
+}
+
+/// What a [`Scanner`] found: every matched folder, including ones that were filtered out.
+pub struct ScanReport {
+    /// All matches sorted by path; filtered ones have `skipped` set
+    pub matches: Vec<CleanMatch>,
+}
+
+impl Scanner {
+    /// Start a scan of `root` with the built-in rules and no filters.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Scanner {
+            roots: vec![root.into()],
+            options: CleanOptions::default(),
+        }
+    }
+
+    /// Scan another root as part of the same run.
+    pub fn root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.roots.push(root.into());
+        self
+    }
+
+    /// Use exactly these rules instead of the built-in ones and any `rs-clean.toml`.
+    pub fn rules(mut self, rules: impl IntoIterator<Item = CleanRule>) -> Self {
+        self.options.rules = Some(rules.into_iter().collect());
+        self
+    }
+
+    /// Read rules from this config file instead of `rs-clean.toml` in the root.
+    pub fn config(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options.config = Some(path.into());
+        self
+    }
+
+    /// Only use rules from the config file.
+    pub fn no_default_rules(mut self) -> Self {
+        self.options.no_default_rules = true;
+        self
+    }
+
+    /// Restrict the scan to these rules (by folder name).
+    pub fn only<S: Into<String>>(mut self, rules: impl IntoIterator<Item = S>) -> Self {
+        self.options.only = rules.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Leave out these rules (by folder name).
+    pub fn skip<S: Into<String>>(mut self, rules: impl IntoIterator<Item = S>) -> Self {
+        self.options.skip = rules.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Only use rules in these categories.
+    pub fn categories(mut self, categories: impl IntoIterator<Item = Category>) -> Self {
+        self.options.categories = categories.into_iter().collect();
+        self
+    }
+
+    /// Maximum directory depth to scan (0 = the root only).
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.options.max_depth = Some(depth);
+        self
+    }
+
+    /// .gitignore-style patterns for folders that must never be cleaned.
+    pub fn exclude<S: Into<String>>(mut self, patterns: impl IntoIterator<Item = S>) -> Self {
+        self.options.exclude = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Never clean folders matched by `rule` under `pattern`.
+    pub fn protect(mut self, rule: impl Into<String>, pattern: impl Into<String>) -> Self {
+        self.options.protect.push(RuleProtect {
+            rule: rule.into(),
+            pattern: pattern.into(),
+        });
+        self
+    }
+
+    /// Skip folders modified within the last `days` days.
+    pub fn older_than(mut self, days: u64) -> Self {
+        self.options.older_than = Some(days);
+        self
+    }
+
+    /// Skip folders not modified within the last `days` days.
+    pub fn newer_than(mut self, days: u64) -> Self {
+        self.options.newer_than = Some(days);
+        self
+    }
+
+    /// Skip folders smaller than `size`.
+    pub fn min_size(mut self, size: ByteSize) -> Self {
+        self.options.min_size = Some(size);
+        self
+    }
+
+    /// Skip folders in git repositories with uncommitted changes.
+    pub fn skip_dirty(mut self) -> Self {
+        self.options.skip_dirty = true;
+        self
+    }
+
+    /// Treat every folder named like a rule as cleanable, even without its project indicator.
+    pub fn ignore_indicator(mut self) -> Self {
+        self.options.ignore_indicator = true;
+        self
+    }
+
+    /// How many directories above a match to look for its project indicator.
+    pub fn indicator_search_depth(mut self, depth: usize) -> Self {
+        self.options.indicator_search_depth = depth;
+        self
+    }
+
+    /// Honor `.gitignore`, `.ignore` and global git excludes.
+    pub fn respect_gitignore(mut self) -> Self {
+        self.options.respect_gitignore = true;
+        self
+    }
+
+    /// Read ignore files from the parent directories of each root.
+    pub fn parents(mut self) -> Self {
+        self.options.parents = true;
+        self
+    }
+
+    /// Descend into hidden (dot-prefixed) directories.
+    pub fn hidden(mut self) -> Self {
+        self.options.hidden = true;
+        self
+    }
+
+    /// Report symlinked matches as cleanable; deleting only ever removes the link.
+    pub fn follow_symlinks(mut self) -> Self {
+        self.options.follow_symlinks = true;
+        self
+    }
+
+    /// Number of scanning threads (defaults to the number of CPUs).
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.options.threads = Some(threads);
+        self
+    }
+
+    /// Walk single-threaded in sorted order for reproducible results.
+    pub fn sequential(mut self) -> Self {
+        self.options.sequential = true;
+        self.options.threads = Some(1);
+        self
+    }
+
+    /// Measure allocated disk blocks instead of apparent file length (Unix only).
+    pub fn on_disk(mut self) -> Self {
+        self.options.on_disk = true;
+        self
+    }
+
+    /// Reuse folder sizes from earlier runs for folders whose mtime hasn't changed.
+    pub fn cache(mut self) -> Self {
+        self.options.cache = true;
+        self
+    }
+
+    /// Give up sizing a single folder after `timeout` and report its size as unknown.
+    pub fn size_timeout(mut self, timeout: Duration) -> Self {
+        self.options.size_timeout = Some(timeout);
+        self
+    }
+
+    /// Scan with deletion in mind, so folders that look in use by a running build are
+    /// skipped as busy. Set this when the report will be passed to [`ScanReport::delete`].
+    pub fn for_deletion(mut self) -> Self {
+        self.options.mode = Mode::Delete;
+        self
+    }
+
+    /// With [`Scanner::for_deletion`], keep folders that look in use instead of skipping them.
+    pub fn force_busy(mut self) -> Self {
+        self.options.force_busy = true;
+        self
+    }
+
+    /// The options this builder has collected, e.g. for [`clean::clean_projects`] to run
+    /// the full CLI flow with its report.
+    pub fn into_options(self) -> (Vec<PathBuf>, CleanOptions) {
+        (self.roots, self.options)
+    }
+
+    /// Run the scan. Nothing is printed and nothing is deleted.
+    pub fn scan(&self) -> Result<ScanReport, CleanError> {
+        let matches = clean::scan_roots(&self.roots, &self.options)?;
+        Ok(ScanReport { matches })
+    }
+}
+
+impl ScanReport {
+    /// Matches that passed every filter and would be removed.
+    pub fn cleanable(&self) -> impl Iterator<Item = &CleanMatch> {
+        self.matches.iter().filter(|m| m.skipped.is_none())
+    }
+
+    /// Matches that were left alone, with the reason in `skipped`.
+    pub fn skipped(&self) -> impl Iterator<Item = &CleanMatch> {
+        self.matches.iter().filter(|m| m.skipped.is_some())
+    }
+
+    /// Total size of the cleanable matches.
+    pub fn reclaimable_bytes(&self) -> u64 {
+        self.cleanable().map(|m| m.size_bytes).sum()
+    }
+
+    /// Remove every cleanable match, moving it to the system trash if `trash` is set.
+    ///
+    /// ```
+    /// use rs_clean::Scanner;
+    /// # let root = std::env::temp_dir().join("rs-clean-doc-delete");
+    /// # let _ = std::fs::remove_dir_all(&root);
+    /// # std::fs::create_dir_all(root.join("crate/target/debug")).unwrap();
+    /// # std::fs::write(root.join("crate/Cargo.toml"), "[package]").unwrap();
+    ///
+    /// let report = Scanner::new(&root).only(["target"]).for_deletion().force_busy().scan()?;
+    /// let deleted = report.delete(false);
+    ///
+    /// assert!(deleted.failed.is_empty());
+    /// assert!(!root.join("crate/target").exists());
+    /// # std::fs::remove_dir_all(&root).unwrap();
+    /// # Ok::<(), rs_clean::clean::CleanError>(())
+    /// ```
+    pub fn delete(&self, trash: bool) -> DeleteReport {
+        clean::delete_matches(&self.matches, trash)
+    }
+}
@@ -0,0 +1,119 @@
+use crossterm::style::{Color, Stylize};
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static COLOR: AtomicBool = AtomicBool::new(false);
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+/// Emoji and box-drawing characters with the plain markers `--ascii` swaps them for.
+const ASCII_MARKERS: &[(&str, &str)] = &[
+    ("⚠️ ", "[!]"),
+    ("❌", "[x]"),
+    ("✅", "[ok]"),
+    ("🎉", "[*]"),
+    ("🚧", "[-]"),
+    ("🌿", "[-]"),
+    ("💾", "[=]"),
+    ("✨", "[*]"),
+    ("💡", "[i]"),
+    ("🔍", "[>]"),
+    ("👀", "[>]"),
+    ("🔁", "[~]"),
+    ("🕒", "[~]"),
+    ("👋", "[i]"),
+    ("📏", "[i]"),
+    ("📄", "[i]"),
+    ("📋", "[=]"),
+    ("📂", "[=]"),
+    ("📊", "[=]"),
+    ("📝", "[>]"),
+    ("⏳", "[-]"),
+    ("🔬", "[-]"),
+    ("⏱️ ", "[i]"),
+    ("🛡️ ", "[-]"),
+    ("🎯", "[=]"),
+    ("📦", "[=]"),
+    ("🧾", "[i]"),
+    ("🧹", "[-]"),
+    ("🗑️ ", "[-]"),
+    ("♻️ ", "[>]"),
+    ("├── ", "|-- "),
+    ("└── ", "`-- "),
+    ("│   ", "|   "),
+];
+
+/// Decide once how output looks: colors are off for `--no-color`, `NO_COLOR` or a piped stdout.
+pub fn init(no_color: bool, ascii: bool) {
+    enable_utf8_console();
+    let env_off = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let enabled = !no_color && !env_off && io::stdout().is_terminal();
+    COLOR.store(enabled, Ordering::Relaxed);
+    ASCII.store(ascii, Ordering::Relaxed);
+}
+
+/// Windows consoles default to a legacy code page that garbles emoji; switch them to UTF-8.
+#[cfg(windows)]
+fn enable_utf8_console() {
+    const CP_UTF8: u32 = 65001;
+    // SAFETY: takes no pointers; on failure the console keeps its current code page
+    unsafe {
+        windows_sys::Win32::System::Console::SetConsoleOutputCP(CP_UTF8);
+    }
+}
+
+#[cfg(not(windows))]
+fn enable_utf8_console() {}
+
+/// `text` with emoji swapped for ASCII markers under `--ascii`.
+pub fn text(text: &str) -> Cow<'_, str> {
+    if !ASCII.load(Ordering::Relaxed) {
+        return Cow::Borrowed(text);
+    }
+    let mut out = text.to_string();
+    for (symbol, marker) in ASCII_MARKERS {
+        out = out.replace(symbol, marker);
+        // Some emoji are followed by a single space in places
+        out = out.replace(symbol.trim_end(), marker);
+    }
+    Cow::Owned(out)
+}
+
+/// `println!` routed through [`text`].
+macro_rules! say {
+    () => {
+        println!()
+    };
+    ($($arg:tt)*) => {
+        println!("{}", $crate::term::text(&format!($($arg)*)))
+    };
+}
+
+/// `eprintln!` routed through [`text`].
+macro_rules! esay {
+    ($($arg:tt)*) => {
+        eprintln!("{}", $crate::term::text(&format!($($arg)*)))
+    };
+}
+
+pub(crate) use {esay, say};
+
+/// `text` in `color`, or unchanged when colors are off.
+pub fn paint(text: impl Display, color: Color) -> String {
+    if COLOR.load(Ordering::Relaxed) {
+        text.to_string().with(color).to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// Print `❌ Error: <message>` to stderr.
+pub fn error(message: impl Display) {
+    esay!("{} {}", paint("❌ Error:", Color::Red), message);
+}
+
+/// Print `⚠️  Warning: <message>` to stderr.
+pub fn warning(message: impl Display) {
+    esay!("{} {}", paint("⚠️  Warning:", Color::Yellow), message);
+}
@@ -1,38 +1,241 @@
+use crate::clean::{self, CleanMatch, CleanOptions};
+use crate::combine::{self, CombineOptions};
+use bytesize::ByteSize;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::{
     Frame, Terminal,
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Position, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
 };
-use std::{error::Error, io, time::Duration};
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    error::Error,
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Two clicks on the same row within this window count as a double-click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Built-in TUI color schemes.
+#[derive(Clone, Copy, Default, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    /// Light text on a dark terminal
+    #[default]
+    Dark,
+    /// Dark text on a light terminal
+    Light,
+    /// Bright colors on black for maximum legibility
+    HighContrast,
+}
+
+/// Colors used to draw the TUI.
+pub struct Theme {
+    title: Color,
+    text: Color,
+    highlight_fg: Color,
+    highlight_bg: Color,
+    /// Footer when something is selected
+    selected: Color,
+    /// Footer when nothing is selected
+    muted: Color,
+    /// Directories in the file picker
+    accent: Color,
+    /// Border of the delete confirmation
+    danger: Color,
+}
+
+impl Theme {
+    pub fn preset(name: ThemeName) -> Theme {
+        match name {
+            ThemeName::Dark => Theme {
+                title: Color::Cyan,
+                text: Color::White,
+                highlight_fg: Color::White,
+                highlight_bg: Color::Blue,
+                selected: Color::Yellow,
+                muted: Color::DarkGray,
+                accent: Color::Cyan,
+                danger: Color::Red,
+            },
+            ThemeName::Light => Theme {
+                title: Color::Blue,
+                text: Color::Black,
+                highlight_fg: Color::Black,
+                highlight_bg: Color::LightCyan,
+                selected: Color::Magenta,
+                muted: Color::Gray,
+                accent: Color::Blue,
+                danger: Color::Red,
+            },
+            ThemeName::HighContrast => Theme {
+                title: Color::LightYellow,
+                text: Color::White,
+                highlight_fg: Color::Black,
+                highlight_bg: Color::LightYellow,
+                selected: Color::LightYellow,
+                muted: Color::White,
+                accent: Color::LightCyan,
+                danger: Color::LightRed,
+            },
+        }
+    }
+
+    /// Replace preset colors with the ones set in the config file.
+    pub fn with_overrides(mut self, colors: &ThemeColors) -> Result<Theme, String> {
+        let fields = [
+            (&mut self.title, &colors.title, "title"),
+            (&mut self.text, &colors.text, "text"),
+            (&mut self.highlight_fg, &colors.highlight_fg, "highlight_fg"),
+            (&mut self.highlight_bg, &colors.highlight_bg, "highlight_bg"),
+            (&mut self.selected, &colors.selected, "selected"),
+            (&mut self.muted, &colors.muted, "muted"),
+            (&mut self.accent, &colors.accent, "accent"),
+            (&mut self.danger, &colors.danger, "danger"),
+        ];
+        for (color, value, key) in fields {
+            if let Some(value) = value {
+                *color = value
+                    .parse()
+                    .map_err(|_| format!("Invalid color {:?} for tui.colors.{}", value, key))?;
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// Per-color overrides from the `[tui.colors]` config table, by name or `#rrggbb`.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ThemeColors {
+    pub title: Option<String>,
+    pub text: Option<String>,
+    pub highlight_fg: Option<String>,
+    pub highlight_bg: Option<String>,
+    pub selected: Option<String>,
+    pub muted: Option<String>,
+    pub accent: Option<String>,
+    pub danger: Option<String>,
+}
+
+/// Messages sent from the background scan thread to the UI.
+enum ScanEvent {
+    Found(CleanMatch),
+    Done(Result<(), String>),
+}
+
+/// Which screen the TUI is showing.
+#[derive(PartialEq)]
+enum Screen {
+    Menu,
+    PathInput,
+    Results,
+    /// Project tree for picking files to combine
+    Picker,
+    OutputInput,
+}
+
+/// What the path typed on the `PathInput` screen is for.
+#[derive(PartialEq)]
+enum Task {
+    Clean,
+    Combine,
+}
+
+/// A file or directory in the combine picker's tree.
+struct TreeEntry {
+    path: PathBuf,
+    depth: usize,
+    is_dir: bool,
+    size: u64,
+}
+
+/// Whether key presses drive the results list or the delete confirmation dialog.
+#[derive(PartialEq)]
+enum Mode {
+    Browsing,
+    Confirming,
+}
 
 struct App {
+    screen: Screen,
     items: Vec<String>,
     state: ListState,
+    path_input: String,
+    matches: Vec<CleanMatch>,
+    results_state: ListState,
+    selected: HashSet<usize>,
+    scan_rx: Option<Receiver<ScanEvent>>,
+    mode: Mode,
+    status: String,
+    task: Task,
+    tree: Vec<TreeEntry>,
+    tree_state: ListState,
+    output_input: String,
+    /// Where the list was drawn last frame, for paging and mouse clicks
+    list_area: Rect,
+    /// Time and row of the last left click, to spot double-clicks
+    last_click: Option<(Instant, usize)>,
 }
 
 impl App {
     fn new() -> App {
         App {
+            screen: Screen::Menu,
             items: vec![
-                "Scan Projects (Not fully implemented in TUI yet)".to_string(),
+                "Scan Projects".to_string(),
+                "Combine Code".to_string(),
                 "Quit".to_string(),
             ],
             state: ListState::default(),
+            path_input: ".".to_string(),
+            matches: Vec::new(),
+            results_state: ListState::default(),
+            selected: HashSet::new(),
+            scan_rx: None,
+            mode: Mode::Browsing,
+            status: String::new(),
+            task: Task::Clean,
+            tree: Vec::new(),
+            tree_state: ListState::default(),
+            output_input: "combined.md".to_string(),
+            list_area: Rect::default(),
+            last_click: None,
+        }
+    }
+
+    /// The list the cursor keys currently move through, and its length.
+    fn active_list(&mut self) -> (&mut ListState, usize) {
+        match self.screen {
+            Screen::Results => (&mut self.results_state, self.matches.len()),
+            Screen::Picker => (&mut self.tree_state, self.tree.len()),
+            _ => (&mut self.state, self.items.len()),
         }
     }
 
     fn next(&mut self) {
-        let i = match self.state.selected() {
+        let (state, len) = self.active_list();
+        if len == 0 {
+            return;
+        }
+        let i = match state.selected() {
             Some(i) => {
-                if i >= self.items.len() - 1 {
+                if i >= len - 1 {
                     0
                 } else {
                     i + 1
@@ -40,25 +243,285 @@ impl App {
             }
             None => 0,
         };
-        self.state.select(Some(i));
+        state.select(Some(i));
     }
 
     fn previous(&mut self) {
-        let i = match self.state.selected() {
+        let (state, len) = self.active_list();
+        if len == 0 {
+            return;
+        }
+        let i = match state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.items.len() - 1
+                    len - 1
                 } else {
                     i - 1
                 }
             }
             None => 0,
         };
-        self.state.select(Some(i));
+        state.select(Some(i));
+    }
+
+    /// Move the cursor to row `to` of the active list, clamped to its last row.
+    fn jump(&mut self, to: impl FnOnce(usize) -> usize) {
+        let (state, len) = self.active_list();
+        if len == 0 {
+            return;
+        }
+        let current = state.selected().unwrap_or(0);
+        state.select(Some(to(current).min(len - 1)));
+    }
+
+    /// Handle the list movement keys shared by every list screen.
+    fn navigate(&mut self, code: KeyCode) -> bool {
+        // Inside the list's borders; ratatui scrolls the list to keep the cursor in view
+        let page = self.list_area.height.saturating_sub(2).max(1) as usize;
+        match code {
+            KeyCode::Down => self.next(),
+            KeyCode::Up => self.previous(),
+            KeyCode::PageDown => self.jump(|i| i.saturating_add(page)),
+            KeyCode::PageUp => self.jump(|i| i.saturating_sub(page)),
+            KeyCode::Home | KeyCode::Char('g') => self.jump(|_| 0),
+            KeyCode::End | KeyCode::Char('G') => self.jump(|_| usize::MAX),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Keep every list's cursor on a real row and forget clicks made on the old layout.
+    fn fit_to_resize(&mut self) {
+        for (state, len) in [
+            (&mut self.state, self.items.len()),
+            (&mut self.results_state, self.matches.len()),
+            (&mut self.tree_state, self.tree.len()),
+        ] {
+            if let Some(i) = state.selected() {
+                state.select((len > 0).then(|| i.min(len - 1)));
+            }
+            // Let the next draw scroll from the top down to the cursor in the new height
+            *state.offset_mut() = 0;
+        }
+        self.last_click = None;
+    }
+
+    /// The row of the active list drawn at a terminal cell, if any.
+    fn row_at(&mut self, column: u16, row: u16) -> Option<usize> {
+        let inner = self.list_area.inner(Margin::new(1, 1));
+        if !inner.contains(Position::new(column, row)) {
+            return None;
+        }
+        let (state, len) = self.active_list();
+        let i = state.offset() + (row - inner.y) as usize;
+        (i < len).then_some(i)
+    }
+
+    /// Start scanning `path_input` on a background thread, streaming matches back.
+    fn start_scan(&mut self) {
+        let root = PathBuf::from(self.path_input.trim());
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            // Scanned for deletion, so folders a running build is using are left out
+            let options = CleanOptions {
+                mode: clean::Mode::Delete,
+                ..CleanOptions::default()
+            };
+            let result = clean::scan_matches_with(&root, &options, |m| {
+                if m.skipped.is_none() {
+                    let _ = tx.send(ScanEvent::Found(m.clone()));
+                }
+            });
+            let _ = tx.send(ScanEvent::Done(
+                result.map(|_| ()).map_err(|e| e.to_string()),
+            ));
+        });
+
+        self.matches.clear();
+        self.selected.clear();
+        self.results_state.select(None);
+        self.scan_rx = Some(rx);
+        self.status = format!("Scanning {}...", self.path_input.trim());
+        self.screen = Screen::Results;
+    }
+
+    /// Pull any matches the scan thread has found since the last frame.
+    fn drain_scan(&mut self) {
+        let Some(rx) = &self.scan_rx else {
+            return;
+        };
+        let mut finished = false;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                ScanEvent::Found(m) => self.matches.push(m),
+                ScanEvent::Done(Ok(())) => {
+                    self.status = format!("Scan complete: {} folder(s) found", self.matches.len());
+                    finished = true;
+                }
+                ScanEvent::Done(Err(e)) => {
+                    self.status = format!("Error: {}", e);
+                    finished = true;
+                }
+            }
+        }
+        if finished {
+            self.scan_rx = None;
+        }
+        if self.results_state.selected().is_none() && !self.matches.is_empty() {
+            self.results_state.select(Some(0));
+        }
+    }
+
+    /// List the files under `path_input` (honoring .gitignore) for the combine picker.
+    fn load_tree(&mut self) {
+        let root = PathBuf::from(self.path_input.trim());
+        if !root.is_dir() {
+            self.status = format!("Error: {:?} is not a directory", root);
+            return;
+        }
+        self.tree = ignore::WalkBuilder::new(&root)
+            .require_git(false)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.depth() > 0)
+            .map(|e| TreeEntry {
+                is_dir: e.file_type().is_some_and(|t| t.is_dir()),
+                size: e.metadata().map(|m| m.len()).unwrap_or(0),
+                depth: e.depth(),
+                path: e.into_path(),
+            })
+            .collect();
+        self.selected.clear();
+        self.tree_state.select((!self.tree.is_empty()).then_some(0));
+        self.status = format!("{} entries", self.tree.len());
+        self.screen = Screen::Picker;
+    }
+
+    /// Indices of the files inside the directory at `i`, or just `i` for a file.
+    fn files_under(&self, i: usize) -> Vec<usize> {
+        let entry = &self.tree[i];
+        if !entry.is_dir {
+            return vec![i];
+        }
+        (i + 1..self.tree.len())
+            .take_while(|&j| self.tree[j].depth > entry.depth)
+            .filter(|&j| !self.tree[j].is_dir)
+            .collect()
+    }
+
+    fn toggle_selected(&mut self) {
+        if self.screen == Screen::Picker {
+            // A directory toggles every file beneath it
+            let Some(i) = self.tree_state.selected() else {
+                return;
+            };
+            let files = self.files_under(i);
+            if files.iter().all(|j| self.selected.contains(j)) {
+                files.iter().for_each(|j| {
+                    self.selected.remove(j);
+                });
+            } else {
+                self.selected.extend(files);
+            }
+        } else if let Some(i) = self.results_state.selected()
+            && !self.selected.remove(&i)
+        {
+            self.selected.insert(i);
+        }
+    }
+
+    fn select_all(&mut self) {
+        self.selected = match self.screen {
+            Screen::Picker => (0..self.tree.len())
+                .filter(|&i| !self.tree[i].is_dir)
+                .collect(),
+            _ => (0..self.matches.len()).collect(),
+        };
+    }
+
+    /// Number of selected folders (or files, in the picker) and their combined size.
+    fn selected_totals(&self) -> (usize, u64) {
+        let bytes = self
+            .selected
+            .iter()
+            .map(|&i| match self.screen {
+                Screen::Picker | Screen::OutputInput => self.tree[i].size,
+                _ => self.matches[i].size_bytes,
+            })
+            .sum();
+        (self.selected.len(), bytes)
+    }
+
+    /// Combine the picked files into `output_input` and report the stats.
+    fn combine_selected(&mut self) {
+        let mut picked: Vec<usize> = self.selected.iter().copied().collect();
+        picked.sort_unstable();
+        let options = CombineOptions {
+            paths: picked
+                .iter()
+                .map(|&i| self.tree[i].path.to_string_lossy().into_owned())
+                .collect(),
+            output: Some(PathBuf::from(self.output_input.trim())),
+            quiet: true,
+            ..CombineOptions::default()
+        };
+        let root = Path::new(self.path_input.trim());
+        self.status = match combine::combine_code(root, &options) {
+            Ok(stats) => format!(
+                "Combined {} file(s), {} line(s), {}, ~{} tokens into {}",
+                stats.files,
+                stats.lines,
+                ByteSize(stats.total_bytes),
+                stats.tokens,
+                self.output_input.trim()
+            ),
+            Err(e) => format!("Error: {}", e),
+        };
+        self.screen = Screen::Picker;
+    }
+
+    /// Ask before deleting, unless the scanned root is too broad to delete under at all.
+    fn confirm_delete(&mut self) {
+        let root = PathBuf::from(self.path_input.trim());
+        match clean::broad_root(&root) {
+            Some(reason) => {
+                self.status = format!("Refusing to delete under {:?}: {}", root, reason);
+            }
+            None => self.mode = Mode::Confirming,
+        }
+    }
+
+    /// Delete every selected folder and drop the removed ones from the list.
+    fn delete_selected(&mut self) {
+        let selected: Vec<CleanMatch> = self
+            .selected
+            .iter()
+            .map(|&i| self.matches[i].clone())
+            .collect();
+        let report = clean::delete_matches(&selected, false);
+
+        self.matches.retain(|m| !report.deleted.contains(&m.path));
+        self.selected.clear();
+        self.results_state
+            .select((!self.matches.is_empty()).then_some(0));
+        self.status = if report.failed.is_empty() {
+            format!(
+                "Deleted {} folder(s), freed {}",
+                report.deleted.len(),
+                ByteSize(report.freed_bytes)
+            )
+        } else {
+            format!(
+                "Deleted {} folder(s), {} failed",
+                report.deleted.len(),
+                report.failed.len()
+            )
+        };
     }
 }
 
-pub fn run_tui() -> Result<(), Box<dyn Error>> {
+pub fn run_tui(theme: Theme) -> Result<(), Box<dyn Error>> {
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -68,7 +531,7 @@ pub fn run_tui() -> Result<(), Box<dyn Error>> {
 
     // create app and run it
     let app = App::new();
-    let res = run_app(&mut terminal, app);
+    let res = run_app(&mut terminal, app, &theme);
 
     // restore terminal
     disable_raw_mode()?;
@@ -79,71 +542,357 @@ pub fn run_tui() -> Result<(), Box<dyn Error>> {
     )?;
     terminal.show_cursor()?;
 
-    if let Err(err) = res {
-        println!("{:?}", err)
-    }
-
+    // Only report run errors once the terminal is usable again
+    res?;
     Ok(())
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
+fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App, theme: &Theme) -> io::Result<()> {
     loop {
-        terminal.draw(|f| ui(f, &mut app))?;
+        app.drain_scan();
+        terminal.draw(|f| ui(f, &mut app, theme))?;
 
         if crossterm::event::poll(Duration::from_millis(250))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => return Ok(()),
-                    KeyCode::Down => app.next(),
-                    KeyCode::Up => app.previous(),
-                    KeyCode::Enter => {
-                        // Placeholder action
-                        if let Some(selected) = app.state.selected() {
-                            if selected == 1 {
-                                // Quit
-                                return Ok(());
-                            }
-                        }
-                    }
-                    _ => {}
+            let quit = match event::read()? {
+                Event::Key(key) => handle_key(&mut app, key.code),
+                Event::Mouse(mouse) => handle_mouse(&mut app, mouse),
+                Event::Resize(width, height) => {
+                    // Clear and redraw at the new size now rather than on the next tick
+                    terminal.resize(Rect::new(0, 0, width, height))?;
+                    app.fit_to_resize();
+                    false
+                }
+                _ => false,
+            };
+            if quit {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Apply a key press to the current screen; returns true when the TUI should exit.
+fn handle_key(app: &mut App, code: KeyCode) -> bool {
+    match app.screen {
+        Screen::Menu => match code {
+            KeyCode::Char('q') => return true,
+            code if app.navigate(code) => {}
+            KeyCode::Enter => match app.state.selected() {
+                Some(0) => {
+                    app.task = Task::Clean;
+                    app.screen = Screen::PathInput;
                 }
+                Some(1) => {
+                    app.task = Task::Combine;
+                    app.screen = Screen::PathInput;
+                }
+                Some(2) => return true,
+                _ => {}
+            },
+            _ => {}
+        },
+        Screen::PathInput => match code {
+            KeyCode::Esc => app.screen = Screen::Menu,
+            KeyCode::Enter if app.task == Task::Combine => app.load_tree(),
+            KeyCode::Enter => app.start_scan(),
+            KeyCode::Backspace => {
+                app.path_input.pop();
+            }
+            KeyCode::Char(c) => app.path_input.push(c),
+            _ => {}
+        },
+        Screen::Results if app.mode == Mode::Confirming => match code {
+            KeyCode::Char('y') => {
+                app.mode = Mode::Browsing;
+                app.delete_selected();
+            }
+            KeyCode::Esc | KeyCode::Char('n') => {
+                app.mode = Mode::Browsing;
+                app.status = "Deletion cancelled".to_string();
+            }
+            _ => {}
+        },
+        Screen::Results => match code {
+            KeyCode::Esc | KeyCode::Char('q') => app.screen = Screen::Menu,
+            code if app.navigate(code) => {}
+            KeyCode::Char(' ') => app.toggle_selected(),
+            KeyCode::Char('a') => app.select_all(),
+            KeyCode::Char('n') => app.selected.clear(),
+            // Wait for the scan to finish so indices stay stable while deleting
+            KeyCode::Char('d') if app.scan_rx.is_none() && !app.selected.is_empty() => {
+                app.confirm_delete();
             }
+            _ => {}
+        },
+        Screen::Picker => match code {
+            // The menu footer totals scan matches, so file picks can't carry over
+            KeyCode::Esc | KeyCode::Char('q') => {
+                app.selected.clear();
+                app.screen = Screen::Menu;
+            }
+            code if app.navigate(code) => {}
+            KeyCode::Char(' ') => app.toggle_selected(),
+            KeyCode::Char('a') => app.select_all(),
+            KeyCode::Char('n') => app.selected.clear(),
+            KeyCode::Char('c') if !app.selected.is_empty() => {
+                app.screen = Screen::OutputInput;
+            }
+            _ => {}
+        },
+        Screen::OutputInput => match code {
+            KeyCode::Esc => app.screen = Screen::Picker,
+            KeyCode::Enter => app.combine_selected(),
+            KeyCode::Backspace => {
+                app.output_input.pop();
+            }
+            KeyCode::Char(c) => app.output_input.push(c),
+            _ => {}
+        },
+    }
+    false
+}
+
+/// Clicks select a list row, the wheel moves the selection and a double-click acts like Enter.
+fn handle_mouse(app: &mut App, mouse: MouseEvent) -> bool {
+    let on_list = match app.screen {
+        Screen::Menu | Screen::Picker => true,
+        Screen::Results => app.mode == Mode::Browsing,
+        Screen::PathInput | Screen::OutputInput => false,
+    };
+    if !on_list {
+        return false;
+    }
+    match mouse.kind {
+        // Unlike the arrow keys, the wheel stops at the ends instead of wrapping
+        MouseEventKind::ScrollDown => app.jump(|i| i + 1),
+        MouseEventKind::ScrollUp => app.jump(|i| i.saturating_sub(1)),
+        MouseEventKind::Down(MouseButton::Left) => {
+            let Some(i) = app.row_at(mouse.column, mouse.row) else {
+                return false;
+            };
+            app.active_list().0.select(Some(i));
+            let now = Instant::now();
+            let double = app
+                .last_click
+                .is_some_and(|(at, row)| row == i && now.duration_since(at) < DOUBLE_CLICK);
+            if double {
+                app.last_click = None;
+                return handle_key(app, KeyCode::Enter);
+            }
+            app.last_click = Some((now, i));
         }
+        _ => {}
     }
+    false
 }
 
-fn ui(frame: &mut Frame, app: &mut App) {
+fn ui(frame: &mut Frame, app: &mut App, theme: &Theme) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(10), Constraint::Percentage(90)].as_ref())
+        .constraints(
+            [
+                Constraint::Percentage(10),
+                Constraint::Min(0),
+                Constraint::Length(3),
+            ]
+            .as_ref(),
+        )
         .split(frame.area()); // Changed size() to area() for newer ratatui
 
+    app.list_area = chunks[1];
+
     let title = Paragraph::new("RS-CLEAN TUI")
         .style(
             Style::default()
-                .fg(Color::Cyan)
+                .fg(theme.title)
                 .add_modifier(Modifier::BOLD),
         )
         .block(Block::default().borders(Borders::ALL));
     frame.render_widget(title, chunks[0]);
 
-    let items: Vec<ListItem> = app
-        .items
-        .iter()
-        .map(|i| {
-            let lines = vec![Line::from(Span::raw(i))]; // Spans to Lines
-            ListItem::new(lines).style(Style::default().fg(Color::White))
-        })
-        .collect();
-
-    let items = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Menu"))
-        .highlight_style(
-            Style::default()
-                .bg(Color::Blue)
-                .add_modifier(Modifier::BOLD),
-        )
-        .highlight_symbol(">> ");
+    match app.screen {
+        Screen::Menu => {
+            let items: Vec<ListItem> = app
+                .items
+                .iter()
+                .map(|i| {
+                    let lines = vec![Line::from(Span::raw(i))]; // Spans to Lines
+                    ListItem::new(lines).style(Style::default().fg(theme.text))
+                })
+                .collect();
+
+            let items = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title("Menu"))
+                .highlight_style(
+                    Style::default()
+                        .fg(theme.highlight_fg)
+                        .bg(theme.highlight_bg)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .highlight_symbol(">> ");
+
+            frame.render_stateful_widget(items, chunks[1], &mut app.state);
+        }
+        Screen::PathInput => {
+            let input = Paragraph::new(format!("{}_", app.path_input))
+                .style(Style::default().fg(theme.text))
+                .block(Block::default().borders(Borders::ALL).title(
+                    if app.task == Task::Combine {
+                        "Project to combine (Enter to browse, Esc to go back)"
+                    } else {
+                        "Path to scan (Enter to start, Esc to go back)"
+                    },
+                ));
+            frame.render_widget(input, chunks[1]);
+        }
+        Screen::Picker | Screen::OutputInput => {
+            let items: Vec<ListItem> = app
+                .tree
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| {
+                    let name = entry
+                        .path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    let indent = "  ".repeat(entry.depth - 1);
+                    let line = if entry.is_dir {
+                        format!("    {}{}/", indent, name)
+                    } else {
+                        let checkbox = if app.selected.contains(&i) {
+                            "[x] "
+                        } else {
+                            "[ ] "
+                        };
+                        format!("{}{}{}  ({})", checkbox, indent, name, ByteSize(entry.size))
+                    };
+                    let color = if entry.is_dir {
+                        theme.accent
+                    } else {
+                        theme.text
+                    };
+                    ListItem::new(Line::from(Span::raw(line))).style(Style::default().fg(color))
+                })
+                .collect();
+
+            let title = format!(
+                "Files - {} (Space: toggle, a: all, n: none, c: combine selected, Esc: back)",
+                app.status
+            );
+            let items = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .highlight_style(
+                    Style::default()
+                        .fg(theme.highlight_fg)
+                        .bg(theme.highlight_bg)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .highlight_symbol(">> ");
+
+            frame.render_stateful_widget(items, chunks[1], &mut app.tree_state);
+
+            if app.screen == Screen::OutputInput {
+                let input = Paragraph::new(format!("{}_", app.output_input))
+                    .style(Style::default().fg(theme.text))
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title("Write combined output to (Enter to write, Esc to cancel)"),
+                    );
+                let area = centered_rect(60, 3, chunks[1]);
+                frame.render_widget(Clear, area);
+                frame.render_widget(input, area);
+            }
+        }
+        Screen::Results => {
+            let items: Vec<ListItem> = app
+                .matches
+                .iter()
+                .enumerate()
+                .map(|(i, m)| {
+                    let checkbox = if app.selected.contains(&i) {
+                        "[x] "
+                    } else {
+                        "[ ] "
+                    };
+                    let line = format!(
+                        "{}{:>10}  {:<12}  {}",
+                        checkbox,
+                        ByteSize(m.size_bytes).to_string(),
+                        m.rule,
+                        m.path.display()
+                    );
+                    ListItem::new(Line::from(Span::raw(line)))
+                        .style(Style::default().fg(theme.text))
+                })
+                .collect();
+
+            let title = format!(
+                "Matches - {} (Space: toggle, a: all, n: none, d: delete selected, Esc: back)",
+                app.status
+            );
+            let items = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .highlight_style(
+                    Style::default()
+                        .fg(theme.highlight_fg)
+                        .bg(theme.highlight_bg)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .highlight_symbol(">> ");
+
+            frame.render_stateful_widget(items, chunks[1], &mut app.results_state);
 
-    frame.render_stateful_widget(items, chunks[1], &mut app.state);
+            if app.mode == Mode::Confirming {
+                let (count, bytes) = app.selected_totals();
+                let text = format!(
+                    "Delete {} folder(s), {}?\n\nThis cannot be undone.\n\ny: delete    n/Esc: cancel",
+                    count,
+                    ByteSize(bytes)
+                );
+                let dialog = Paragraph::new(text)
+                    .alignment(Alignment::Center)
+                    .style(Style::default().fg(theme.text))
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(theme.danger))
+                            .title("Confirm deletion"),
+                    );
+                let area = centered_rect(50, 9, chunks[1]);
+                frame.render_widget(Clear, area);
+                frame.render_widget(dialog, area);
+            }
+        }
+    }
+
+    let (count, bytes) = app.selected_totals();
+    let footer_style = if count > 0 {
+        Style::default()
+            .fg(theme.selected)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(theme.muted)
+    };
+    let unit = match app.screen {
+        Screen::Picker | Screen::OutputInput => "file(s)",
+        _ => "folder(s)",
+    };
+    let footer = Paragraph::new(format!("Selected: {} {}, {}", count, unit, ByteSize(bytes)))
+        .style(footer_style)
+        .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(footer, chunks[2]);
+}
+
+/// A rectangle `percent_x` wide and `height` rows tall, centered in `area`.
+fn centered_rect(percent_x: u16, height: u16, area: Rect) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
 }